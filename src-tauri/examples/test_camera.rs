@@ -1,29 +1,37 @@
 // Test program to verify camera detection
+//
+// Runs without a camera against the simulated ones in examples/fixtures:
+// ALTOID_SIMULATED_CAMERA=examples/fixtures/theta_z1.json cargo run --example test_camera
 fn main() {
     println!("Testing camera detection...\n");
 
-    match altoid_lib::camera::camera::find_camera() {
-        Some(camera_with_files) => {
-            println!("Camera detected:");
-            println!("  Device: {}", camera_with_files.info.device);
-            println!("  Vendor: {}", camera_with_files.info.vendor);
-            println!("  Vendor ID: {}", camera_with_files.info.vendor_id);
-            println!("  Mount point: {:?}", camera_with_files.mount_point);
-            println!("  Files found: {}", camera_with_files.files.len());
+    let cameras = altoid_lib::camera::camera::find_cameras();
+    if cameras.is_empty() {
+        println!("No camera detected");
+    }
 
-            if let Some(error) = &camera_with_files.access_error {
-                println!("  Access error: {}", error);
-            }
+    for camera_with_files in cameras {
+        println!("Camera detected:");
+        println!("  Device: {}", camera_with_files.info.device);
+        println!("  Device ID: {}", camera_with_files.device_id);
+        println!("  Vendor: {}", camera_with_files.info.vendor);
+        println!("  Vendor ID: {}", camera_with_files.info.vendor_id);
+        if let Some(backend) = &camera_with_files.backend {
+            println!("  Backend: {}", backend.name());
+        }
+        println!("  Mount point: {:?}", camera_with_files.mount_point);
+        println!("  Files found: {}", camera_with_files.files.len());
 
-            if !camera_with_files.files.is_empty() {
-                println!("\nFirst 10 files:");
-                for (i, file) in camera_with_files.files.iter().take(10).enumerate() {
-                    println!("  {}. {}", i + 1, file.display());
-                }
-            }
+        if let Some(error) = &camera_with_files.access_error {
+            println!("  Access error: {}", error);
         }
-        None => {
-            println!("No camera detected");
+
+        if !camera_with_files.files.is_empty() {
+            println!("\nFirst 10 files:");
+            for (i, file) in camera_with_files.files.iter().take(10).enumerate() {
+                println!("  {}. {} ({} bytes, {})", i + 1, file.path.display(), file.size, file.content_type);
+            }
         }
+        println!();
     }
 }
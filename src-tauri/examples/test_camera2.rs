@@ -1,29 +1,39 @@
-// Test program to verify camera2 detection using gphoto2-sys
+use altoid_lib::camera::backend::CameraBackend;
+use altoid_lib::camera::libgphoto2::LibGphoto2Backend;
+use altoid_lib::camera::usb::connected_cameras;
+
+// Test program to verify camera access through the libgphoto2 backend only
 fn main() {
-    println!("Testing camera detection with gphoto2-sys (camera2.rs)...\n");
+    println!("Testing camera detection with gphoto2-sys (libgphoto2 backend)...\n");
 
-    match altoid_lib::camera::camera2::find_camera() {
-        Some(camera_with_files) => {
-            println!("Camera detected:");
-            println!("  Device: {}", camera_with_files.info.device);
-            println!("  Vendor: {}", camera_with_files.info.vendor);
-            println!("  Vendor ID: {}", camera_with_files.info.vendor_id);
-            println!("  Mount point: {:?}", camera_with_files.mount_point);
-            println!("  Files found: {}", camera_with_files.files.len());
+    let Some((usb, _)) = connected_cameras().into_iter().next() else {
+        println!("No camera detected");
+        return;
+    };
 
-            if let Some(error) = &camera_with_files.access_error {
-                println!("  Access error: {}", error);
-            }
+    let backend = match LibGphoto2Backend::detect(&usb) {
+        Ok(backend) => backend,
+        Err(e) => {
+            println!("No camera detected: {}", e);
+            return;
+        }
+    };
+
+    match backend.list() {
+        Ok(files) => {
+            println!("Camera detected:");
+            println!("  Backend: {}", backend.name());
+            println!("  Files found: {}", files.len());
 
-            if !camera_with_files.files.is_empty() {
+            if !files.is_empty() {
                 println!("\nFirst 10 files:");
-                for (i, file) in camera_with_files.files.iter().take(10).enumerate() {
-                    println!("  {}. {}", i + 1, file.display());
+                for (i, file) in files.iter().take(10).enumerate() {
+                    println!("  {}. {} ({} bytes, {})", i + 1, file.path.display(), file.size, file.content_type);
                 }
             }
         }
-        None => {
-            println!("No camera detected");
+        Err(e) => {
+            println!("  Access error: {}", e);
         }
     }
 }
@@ -0,0 +1,267 @@
+use crate::error::AppError;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+
+/// Longest `Retry-After` we are willing to wait for before giving up on a
+/// single attempt.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Capped exponential backoff with jitter.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries, for requests that must not be sent twice.
+    pub fn single_attempt() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the attempt following `attempt` (1-based).
+    ///
+    /// Uses "equal jitter": half of the capped exponential delay is fixed and
+    /// the other half is random, so concurrent clients spread out without any
+    /// of them retrying immediately.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let capped = exponential.min(self.max_delay);
+        let half = capped / 2;
+        half + half.mul_f64(rand::rng().random_range(0.0..=1.0))
+    }
+
+    /// Delay before retrying after `attempt` failed with `error`: the
+    /// server's `Retry-After` if it sent one, else `backoff`.
+    pub fn delay_for(&self, attempt: u32, error: &AppError) -> Duration {
+        match error {
+            AppError::ApiRequest {
+                retry_after: Some(retry_after),
+                ..
+            } => (*retry_after).min(MAX_RETRY_AFTER),
+            _ => self.backoff(attempt),
+        }
+    }
+}
+
+/// Runs `operation` until it succeeds, fails with an error that is not
+/// retryable (see `AppError::is_retryable`), or runs out of attempts.
+///
+/// `label` identifies the operation in the logs.
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    label: &str,
+    mut operation: F,
+) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => {
+                if attempt > 1 {
+                    println!(
+                        "{} succeeded on attempt {}/{}",
+                        label, attempt, policy.max_attempts
+                    );
+                }
+                return Ok(value);
+            }
+            Err(e) if e.is_retryable() && attempt < policy.max_attempts => {
+                let delay = policy.delay_for(attempt, &e);
+                eprintln!(
+                    "{} failed on attempt {}/{}: {}. Retrying in {:.1}s",
+                    label,
+                    attempt,
+                    policy.max_attempts,
+                    e,
+                    delay.as_secs_f64()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                if e.is_retryable() {
+                    eprintln!("{} failed after {} attempts: {}", label, attempt, e);
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Parses a `Retry-After` header given either as delay-seconds or as an
+/// HTTP date.
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::cell::Cell;
+
+    fn api_error(status: u16) -> AppError {
+        AppError::ApiRequest {
+            status,
+            message: String::new(),
+            retry_after: None,
+        }
+    }
+
+    fn instant() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    fn retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter() {
+        let policy = RetryPolicy::default();
+        for (attempt, full) in [(1, 500), (2, 1000), (3, 2000)] {
+            let delay = policy.backoff(attempt);
+            let full = Duration::from_millis(full);
+            assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy::default();
+        assert!(policy.backoff(30) <= policy.max_delay);
+        assert!(policy.backoff(u32::MAX) <= policy.max_delay);
+    }
+
+    #[test]
+    fn retry_after_overrides_backoff() {
+        let error = AppError::ApiRequest {
+            status: 429,
+            message: String::new(),
+            retry_after: Some(Duration::from_secs(3600)),
+        };
+        assert_eq!(RetryPolicy::default().delay_for(1, &error), MAX_RETRY_AFTER);
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(
+            parse_retry_after(&retry_after(" 120 ")),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn parses_retry_after_date() {
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(60));
+        assert_eq!(parse_retry_after(&retry_after(&past)), Some(Duration::ZERO));
+
+        let future = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let delay = parse_retry_after(&retry_after(&future)).unwrap();
+        assert!(delay > Duration::from_secs(100) && delay <= Duration::from_secs(120));
+    }
+
+    #[test]
+    fn ignores_invalid_retry_after() {
+        assert_eq!(parse_retry_after(&HeaderMap::new()), None);
+        assert_eq!(parse_retry_after(&retry_after("soon")), None);
+        assert_eq!(parse_retry_after(&retry_after("-5")), None);
+    }
+
+    #[tokio::test]
+    async fn retries_retryable_errors_until_success() {
+        let attempts = Cell::new(0);
+        let result = with_retry(&instant(), "test", || {
+            attempts.set(attempts.get() + 1);
+            async {
+                match attempts.get() {
+                    1 => Err(api_error(503)),
+                    _ => Ok("done"),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn stops_on_errors_that_are_not_retryable() {
+        let attempts = Cell::new(0);
+        let result: Result<(), AppError> = with_retry(&instant(), "test", || {
+            attempts.set(attempts.get() + 1);
+            async { Err(api_error(400)) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let attempts = Cell::new(0);
+        let result: Result<(), AppError> = with_retry(&instant(), "test", || {
+            attempts.set(attempts.get() + 1);
+            async { Err(api_error(500)) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn single_attempt_never_retries() {
+        let attempts = Cell::new(0);
+        let result: Result<(), AppError> =
+            with_retry(&RetryPolicy::single_attempt(), "test", || {
+                attempts.set(attempts.get() + 1);
+                async { Err(api_error(503)) }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -1,4 +1,4 @@
 pub mod openspace;
 pub mod oauth;
 
-mod http;
\ No newline at end of file
+pub mod http;
\ No newline at end of file
@@ -1,34 +1,119 @@
+use crate::api::http::retry::parse_retry_after;
+use crate::api::oauth::pkce::authorization_code_login;
 use crate::api::oauth::pkg_auth::{
-    DeviceCodeRequest, DeviceCodeResponse, TokenRequest, TokenResponse,
+    AuthFlow, AuthScope, DeviceCodeRequest, DeviceCodeResponse, OAuthErrorResponse,
+    RefreshTokenRequest, TokenRequest, TokenResponse,
 };
+use crate::api::oauth::pub_device_code::DeviceCodePrompt;
+use crate::api::openspace::api::reset_api;
 use crate::cache::oauth_cache;
+use crate::cache::pub_oauth_config::OAuthConfig;
 use crate::cache::pub_user_config::UserConfig;
-use crate::cache::user_cache::{get_user_config, save_user_config};
+use crate::cache::user_cache::{clear_user_config, get_user_config, save_user_config};
 use crate::error::AppError;
+use crate::util::time::unix_now;
 use reqwest::Client;
 use std::sync::LazyLock;
 use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
 
-static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+// Event the frontend listens for through `ApiService`
+pub const DEVICE_CODE_EVENT: &str = "login-device-code";
+
+/// Added to the polling interval each time the server answers `slow_down`
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+pub static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
     Client::builder()
         .timeout(Duration::from_secs(30))
         .build()
         .expect("Failed to create HTTP client")
 });
 
-pub async fn authenticate_user() -> Result<UserConfig, AppError> {
-    let login_config = oauth_cache::get_oauth_config()
-        .ok_or(AppError::OAuthConfigNotFound)?;
+/// Bumped by `cancel_login`. Logins in progress stop when it changes.
+static LOGIN_CANCELLATIONS: LazyLock<watch::Sender<u64>> =
+    LazyLock::new(|| watch::Sender::new(0));
+
+/// Listens for `cancel_login`. Taken as soon as a login is asked for,
+/// before anything is awaited, so a cancel sent while it starts is not
+/// missed.
+pub fn login_cancellation() -> watch::Receiver<u64> {
+    LOGIN_CANCELLATIONS.subscribe()
+}
+
+/// Logs in with the flow set in the OAuth config and saves the tokens.
+/// Runs until the user finishes or denies the login in their browser, the
+/// login times out, or `cancel_login` is called after `cancellations` was
+/// taken.
+pub async fn authenticate_user(
+    app: &AppHandle,
+    mut cancellations: watch::Receiver<u64>,
+) -> Result<UserConfig, AppError> {
+    // Dropping the login future stops it wherever it is waiting
+    tokio::select! {
+        result = login(app) => result,
+        _ = cancellations.changed() => Err(AppError::LoginCancelled),
+    }
+}
+
+/// Saves the login config. Tokens issued for another environment or client
+/// are no good with the new config, so changing either logs out.
+pub fn save_login_config(config: &OAuthConfig) -> Result<(), AppError> {
+    // Saving over a config that cannot be read is how it gets fixed
+    let previous = oauth_cache::get_oauth_config().ok();
+    oauth_cache::save_oauth_config(config)?;
+
+    let changed = match previous {
+        Some(previous) => {
+            previous.env != config.env || previous.client_id().ok() != config.client_id().ok()
+        }
+        None => true,
+    };
+    if changed {
+        println!("Login config changed to {}, logging out", config.env);
+        clear_user_config()?;
+        reset_api();
+    }
+    Ok(())
+}
+
+/// Stops every login in progress.
+pub fn cancel_login() {
+    LOGIN_CANCELLATIONS.send_modify(|count| *count += 1);
+}
+
+async fn login(app: &AppHandle) -> Result<UserConfig, AppError> {
+    let login_config = oauth_cache::get_oauth_config()?;
+
+    let token_response = match login_config.flow {
+        AuthFlow::DeviceCode => device_code_login(app, &login_config).await?,
+        AuthFlow::AuthorizationCode => authorization_code_login(&login_config).await?,
+    };
 
+    let user_config = save_user_config(&token_response)?;
+    reset_api();
+
+    Ok(user_config)
+}
+
+/// Device-code flow, emitting `login-device-code` so the UI can show the
+/// code.
+async fn device_code_login(
+    app: &AppHandle,
+    login_config: &OAuthConfig,
+) -> Result<TokenResponse, AppError> {
     let auth_url = login_config.env.get_auth_url();
     let token_url = login_config.env.get_token_url();
     let audience = login_config.env.get_audience();
 
+    let client_id = login_config.client_id()?;
+
     // Step 1: Request device code from auth server
     println!("Requesting device code...");
     let device_code_request = DeviceCodeRequest {
-        client_id: login_config.client_id.clone(),
-        scope: login_config.scope.as_ref().to_string(),
+        client_id: client_id.to_string(),
+        scope: requested_scope(&login_config.scopes),
         audience: audience.to_string(),
     };
 
@@ -46,6 +131,11 @@ pub async fn authenticate_user() -> Result<UserConfig, AppError> {
         device_code_response.user_code
     );
 
+    let prompt = DeviceCodePrompt::new(&device_code_response, unix_now());
+    if let Err(e) = app.emit(DEVICE_CODE_EVENT, &prompt) {
+        eprintln!("Failed to emit {}: {}", DEVICE_CODE_EVENT, e);
+    }
+
     // Step 2: Open browser with verification_uri_complete
     println!("Opening browser for authentication...");
     if let Err(e) = open::that(&device_code_response.verification_uri_complete) {
@@ -60,7 +150,7 @@ pub async fn authenticate_user() -> Result<UserConfig, AppError> {
 
     // Step 3: Poll for token after user authenticates
     println!("Waiting for authentication...");
-    let interval = Duration::from_secs(device_code_response.interval);
+    let mut interval = Duration::from_secs(device_code_response.interval);
     let expires_at =
         std::time::Instant::now() + Duration::from_secs(device_code_response.expires_in);
 
@@ -74,7 +164,7 @@ pub async fn authenticate_user() -> Result<UserConfig, AppError> {
         let token_request = TokenRequest {
             grant_type: "urn:ietf:params:oauth:grant-type:device_code".to_string(),
             device_code: device_code_response.device_code.clone(),
-            client_id: login_config.client_id.clone(),
+            client_id: client_id.to_string(),
         };
 
         let response = HTTP_CLIENT
@@ -90,24 +180,131 @@ pub async fn authenticate_user() -> Result<UserConfig, AppError> {
                 .await
                 .map_err(|e| AppError::ApiParseFailed(e.to_string()))?;
             break token_response;
-        } else {
-            // Check for authorization_pending or slow_down errors (expected during polling)
-            let status = response.status();
-            let error_text = response.text().await;
-
-            match error_text {
-                Ok(text) => println!("Token request failed ({}): {}", status, text),
-                Err(e) => println!("Failed to read error text: {}", e)
+        }
+
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        let Ok(error) = serde_json::from_str::<OAuthErrorResponse>(&error_text) else {
+            // Not an OAuth error, e.g. a proxy error page; try again
+            if status.is_server_error() {
+                eprintln!("Token request failed ({}): {}", status, error_text);
+                continue;
             }
+            return Err(AppError::AuthenticationFailed(format!(
+                "Token request failed ({}): {}",
+                status, error_text
+            )));
+        };
+
+        match poll_error(error) {
+            PollError::Pending => {}
+            PollError::SlowDown => {
+                interval += SLOW_DOWN_STEP;
+                println!("Asked to slow down, polling every {:?}", interval);
+            }
+            PollError::Failed(e) => return Err(e),
         }
     };
 
-    save_user_config(
-        token_response.access_token.clone(),
-        token_response.token_type.clone(),
-    )?;
+    Ok(token_response)
+}
 
-    Ok(get_user_config().ok_or_else(|| AppError::Internal("Failed to get user config after authentication".to_string()))?)
+/// What an error answer to a device code token request means.
+#[derive(Debug)]
+enum PollError {
+    /// The user has not finished logging in yet
+    Pending,
+    /// Polling too often
+    SlowDown,
+    Failed(AppError),
+}
+
+/// Maps the errors of RFC 8628 section 3.5.
+fn poll_error(error: OAuthErrorResponse) -> PollError {
+    match error.error.as_str() {
+        "authorization_pending" => PollError::Pending,
+        "slow_down" => PollError::SlowDown,
+        "access_denied" => PollError::Failed(AppError::AccessDenied),
+        "expired_token" => PollError::Failed(AppError::DeviceCodeExpired),
+        _ => PollError::Failed(AppError::AuthenticationFailed(
+            error.error_description.unwrap_or(error.error),
+        )),
+    }
+}
+
+/// Exchanges the saved refresh token for a new access token and saves it.
+///
+/// Fails with `NotAuthenticated` when there is no refresh token or the
+/// server rejects it. A rejected token is logged out, so the user is asked
+/// to log in again.
+pub async fn refresh_access_token() -> Result<UserConfig, AppError> {
+    let login_config = oauth_cache::get_oauth_config()?;
+    let refresh_token = get_user_config()
+        .and_then(|config| config.refresh_token)
+        .ok_or(AppError::NotAuthenticated)?;
+
+    let refresh_request = RefreshTokenRequest {
+        grant_type: "refresh_token".to_string(),
+        client_id: login_config.client_id()?.to_string(),
+        refresh_token,
+    };
+
+    let response = HTTP_CLIENT
+        .post(login_config.env.get_token_url())
+        .json(&refresh_request)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let retry_after = parse_retry_after(response.headers());
+        let body = response.text().await.unwrap_or_default();
+        let error = refresh_error(status.as_u16(), retry_after, &body);
+        if matches!(error, AppError::NotAuthenticated) {
+            eprintln!("Refresh token rejected ({}): {}", status, body);
+            clear_user_config()?;
+            reset_api();
+        }
+        return Err(error);
+    }
+
+    let token_response: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| AppError::ApiParseFailed(e.to_string()))?;
+    println!("Access token refreshed");
+
+    save_user_config(&token_response)
+}
+
+/// Error for a failed refresh. Only an OAuth error answered with 400 or 401,
+/// typically `invalid_grant` for an expired or revoked token, rejects the
+/// refresh token: `NotAuthenticated`. Anything else, such as being rate
+/// limited, is an `ApiRequest` that may be retried.
+fn refresh_error(status: u16, retry_after: Option<Duration>, body: &str) -> AppError {
+    let rejected =
+        matches!(status, 400 | 401) && serde_json::from_str::<OAuthErrorResponse>(body).is_ok();
+    if rejected {
+        return AppError::NotAuthenticated;
+    }
+    AppError::ApiRequest {
+        status,
+        message: format!("Token refresh failed ({}): {}", status, body),
+        retry_after,
+    }
+}
+
+/// Scope asked for at login. `offline_access` is always added so the server
+/// issues a refresh token.
+pub fn requested_scope(scopes: &[AuthScope]) -> String {
+    let mut requested: Vec<String> = Vec::new();
+    for scope in scopes.iter().chain([&AuthScope::OfflineAccess]) {
+        let scope = scope.to_string();
+        if !requested.contains(&scope) {
+            requested.push(scope);
+        }
+    }
+    requested.join(" ")
 }
 
 pub fn get_user_initials(full_name: Option<String>) -> String {
@@ -136,3 +333,98 @@ pub fn get_user_initials(full_name: Option<String>) -> String {
         _ => "OS".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oauth_error(error: &str, description: Option<&str>) -> OAuthErrorResponse {
+        OAuthErrorResponse {
+            error: error.to_string(),
+            error_description: description.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn keeps_polling_while_pending_or_slowed_down() {
+        assert!(matches!(
+            poll_error(oauth_error("authorization_pending", None)),
+            PollError::Pending
+        ));
+        assert!(matches!(
+            poll_error(oauth_error("slow_down", None)),
+            PollError::SlowDown
+        ));
+    }
+
+    #[test]
+    fn stops_polling_on_denial_or_expiry() {
+        assert!(matches!(
+            poll_error(oauth_error("access_denied", None)),
+            PollError::Failed(AppError::AccessDenied)
+        ));
+        assert!(matches!(
+            poll_error(oauth_error("expired_token", None)),
+            PollError::Failed(AppError::DeviceCodeExpired)
+        ));
+    }
+
+    #[test]
+    fn other_errors_fail_with_their_description() {
+        assert!(matches!(
+            poll_error(oauth_error("invalid_grant", Some("Code already used"))),
+            PollError::Failed(AppError::AuthenticationFailed(message)) if message == "Code already used"
+        ));
+        assert!(matches!(
+            poll_error(oauth_error("invalid_client", None)),
+            PollError::Failed(AppError::AuthenticationFailed(message)) if message == "invalid_client"
+        ));
+    }
+
+    #[test]
+    fn rejected_refresh_token_logs_out() {
+        let body = r#"{"error": "invalid_grant", "error_description": "Expired"}"#;
+        assert!(matches!(
+            refresh_error(400, None, body),
+            AppError::NotAuthenticated
+        ));
+        assert!(matches!(
+            refresh_error(401, None, body),
+            AppError::NotAuthenticated
+        ));
+    }
+
+    #[test]
+    fn rate_limited_refresh_is_retried() {
+        let body = r#"{"error": "too_many_requests"}"#;
+        let error = refresh_error(429, Some(Duration::from_secs(30)), body);
+
+        assert!(matches!(
+            error,
+            AppError::ApiRequest {
+                status: 429,
+                retry_after: Some(_),
+                ..
+            }
+        ));
+        assert!(error.is_retryable());
+        assert!(refresh_error(408, None, "").is_retryable());
+    }
+
+    #[test]
+    fn refresh_without_an_oauth_error_keeps_the_login() {
+        assert!(matches!(
+            refresh_error(401, None, "<html>Unauthorized</html>"),
+            AppError::ApiRequest { status: 401, .. }
+        ));
+    }
+
+    #[test]
+    fn requested_scope_always_has_offline_access_once() {
+        assert_eq!(requested_scope(&[AuthScope::Email]), "email offline_access");
+        assert_eq!(
+            requested_scope(&[AuthScope::OfflineAccess, AuthScope::Email]),
+            "offline_access email"
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod auth;
-pub mod pkg_auth;
\ No newline at end of file
+pub mod pkce;
+pub mod pkg_auth;
+pub mod pub_device_code;
\ No newline at end of file
@@ -0,0 +1,316 @@
+use crate::api::oauth::auth::{requested_scope, HTTP_CLIENT};
+use crate::api::oauth::pkg_auth::{
+    AuthorizationCodeTokenRequest, OAuthErrorResponse, TokenResponse,
+};
+use crate::cache::pub_oauth_config::OAuthConfig;
+use crate::error::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Path of the loopback redirect URL.
+const CALLBACK_PATH: &str = "/callback";
+
+/// How long the browser has to redirect back before the login is abandoned.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How long a connection to the listener has to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request head read from the browser. The redirect only carries a
+/// short query string.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// What the browser sent back to the redirect URL.
+enum Redirect {
+    Code(String),
+    Error(OAuthErrorResponse),
+}
+
+/// Logs in with the authorization-code flow and PKCE: opens the browser on
+/// the authorization page and receives the code on a listener on
+/// 127.0.0.1, which only lives for the duration of the login.
+pub async fn authorization_code_login(
+    login_config: &OAuthConfig,
+) -> Result<TokenResponse, AppError> {
+    let listener =
+        TcpListener::bind(("127.0.0.1", login_config.redirect_port.unwrap_or(0))).await?;
+    let redirect_uri = format!(
+        "http://127.0.0.1:{}{}",
+        listener.local_addr()?.port(),
+        CALLBACK_PATH
+    );
+
+    // RFC 7636: the verifier never leaves this process, only its hash does
+    let code_verifier = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+    let code_challenge = code_challenge(&code_verifier);
+    let state = hex::encode(rand::random::<[u8; 16]>());
+
+    let authorize_url = Url::parse_with_params(
+        &login_config.env.get_authorize_url(),
+        &[
+            ("response_type", "code"),
+            ("client_id", login_config.client_id()?),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("scope", requested_scope(&login_config.scopes).as_str()),
+            ("audience", login_config.env.get_audience()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+            ("state", state.as_str()),
+        ],
+    )
+    .map_err(|e| AppError::Internal(format!("Invalid authorization URL: {}", e)))?;
+
+    println!("Opening browser for authentication...");
+    if let Err(e) = open::that(authorize_url.as_str()) {
+        eprintln!("Failed to open browser automatically: {}", e);
+        println!("Please manually visit: {}", authorize_url);
+    }
+
+    println!("Waiting for the browser on {}...", redirect_uri);
+    let code = match tokio::time::timeout(LOGIN_TIMEOUT, receive_redirect(&listener, &state)).await
+    {
+        Ok(Ok(Redirect::Code(code))) => code,
+        Ok(Ok(Redirect::Error(error))) => return Err(login_error(error)),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => {
+            return Err(AppError::AuthenticationFailed(
+                "Timed out waiting for the browser".to_string(),
+            ))
+        }
+    };
+
+    println!("Authorization code received, requesting tokens...");
+    let token_request = AuthorizationCodeTokenRequest {
+        grant_type: "authorization_code".to_string(),
+        client_id: login_config.client_id()?.to_string(),
+        code,
+        code_verifier,
+        redirect_uri,
+    };
+
+    let response = HTTP_CLIENT
+        .post(login_config.env.get_token_url())
+        .json(&token_request)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(
+            match serde_json::from_str::<OAuthErrorResponse>(&error_text) {
+                Ok(error) => login_error(error),
+                Err(_) => AppError::AuthenticationFailed(format!(
+                    "Token request failed ({}): {}",
+                    status, error_text
+                )),
+            },
+        );
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| AppError::ApiParseFailed(e.to_string()))
+}
+
+/// The S256 `code_challenge` sent in place of `code_verifier`.
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// Serves requests to the listener until the browser is redirected back with
+/// this login's `state`. Each connection is served on its own task, so one
+/// the browser opens ahead of time and leaves idle does not hold up the
+/// others.
+async fn receive_redirect(listener: &TcpListener, state: &str) -> Result<Redirect, AppError> {
+    let (redirects, mut received) = mpsc::channel(1);
+    let state: Arc<str> = Arc::from(state);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                tokio::spawn(serve_connection(stream, state.clone(), redirects.clone()));
+            }
+            Some(redirect) = received.recv() => return Ok(redirect),
+        }
+    }
+}
+
+/// Answers one connection to the listener, passing on the redirect if it is
+/// the one for this login. Anything else, such as the browser asking for a
+/// favicon, is answered and ignored.
+async fn serve_connection(
+    mut stream: TcpStream,
+    state: Arc<str>,
+    redirects: mpsc::Sender<Redirect>,
+) {
+    let params = match tokio::time::timeout(READ_TIMEOUT, read_callback(&mut stream)).await {
+        Ok(Ok(Some(params))) => params,
+        Ok(Ok(None)) => {
+            respond(&mut stream, "404 Not Found", "Not found.").await;
+            return;
+        }
+        Ok(Err(e)) => {
+            eprintln!("Failed to read request on the login listener: {}", e);
+            return;
+        }
+        // Nothing sent, e.g. a connection the browser opened in advance
+        Err(_) => return,
+    };
+
+    // Another page could send the browser here; only our own login counts
+    if params.get("state").map(String::as_str) != Some(&*state) {
+        eprintln!("Ignoring login redirect with an unknown state");
+        respond(&mut stream, "400 Bad Request", "Unknown login request.").await;
+        return;
+    }
+
+    let redirect = if let Some(code) = params.get("code") {
+        respond(
+            &mut stream,
+            "200 OK",
+            "Login complete. You can close this window and return to the app.",
+        )
+        .await;
+        Redirect::Code(code.clone())
+    } else {
+        respond(
+            &mut stream,
+            "200 OK",
+            "Login failed. Please return to the app.",
+        )
+        .await;
+        Redirect::Error(OAuthErrorResponse {
+            error: params
+                .get("error")
+                .cloned()
+                .unwrap_or_else(|| "invalid_request".to_string()),
+            error_description: params.get("error_description").cloned(),
+        })
+    };
+
+    // Fails only once the login has already received its redirect
+    let _ = redirects.send(redirect).await;
+}
+
+/// Reads the request head and returns its query parameters if it is a
+/// request for the callback path.
+async fn read_callback(
+    stream: &mut (impl AsyncRead + Unpin),
+) -> Result<Option<HashMap<String, String>>, AppError> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    // A request line like "GET /callback?code=...&state=... HTTP/1.1"
+    let head = String::from_utf8_lossy(&head);
+    let Some(target) = head.lines().next().and_then(|line| line.split(' ').nth(1)) else {
+        return Ok(None);
+    };
+    let url = Url::parse(&format!("http://127.0.0.1{}", target))
+        .map_err(|e| AppError::InvalidArgument(format!("Invalid redirect: {}", e)))?;
+
+    if url.path() != CALLBACK_PATH {
+        return Ok(None);
+    }
+    Ok(Some(url.query_pairs().into_owned().collect()))
+}
+
+/// Answers the browser with a plain text page. Failures are only logged: the
+/// login does not depend on the browser showing it.
+async fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        message.len(),
+        message
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        eprintln!("Failed to answer the browser: {}", e);
+    }
+}
+
+fn login_error(error: OAuthErrorResponse) -> AppError {
+    match error.error.as_str() {
+        "access_denied" => AppError::AccessDenied,
+        _ => AppError::AuthenticationFailed(error.error_description.unwrap_or(error.error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(request: &str) -> Option<HashMap<String, String>> {
+        read_callback(&mut request.as_bytes()).await.unwrap()
+    }
+
+    #[test]
+    fn code_challenge_is_s256() {
+        // RFC 7636 appendix B
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_the_callback_query() {
+        let params =
+            read("GET /callback?code=abc%2F123&state=xyz HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n")
+                .await
+                .unwrap();
+        assert_eq!(params["code"], "abc/123");
+        assert_eq!(params["state"], "xyz");
+    }
+
+    #[tokio::test]
+    async fn reads_a_login_error() {
+        let params = read(
+            "GET /callback?error=access_denied&error_description=Denied+by+user HTTP/1.1\r\n\r\n",
+        )
+        .await
+        .unwrap();
+        assert_eq!(params["error"], "access_denied");
+        assert_eq!(params["error_description"], "Denied by user");
+    }
+
+    #[tokio::test]
+    async fn ignores_other_paths() {
+        assert!(read("GET /favicon.ico HTTP/1.1\r\n\r\n").await.is_none());
+        assert!(read("GET /callback/extra?code=abc HTTP/1.1\r\n\r\n")
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn ignores_requests_without_a_target() {
+        assert!(read("").await.is_none());
+        assert!(read("GARBAGE\r\n\r\n").await.is_none());
+    }
+
+    #[test]
+    fn maps_a_denied_login() {
+        let error = OAuthErrorResponse {
+            error: "access_denied".to_string(),
+            error_description: None,
+        };
+        assert!(matches!(login_error(error), AppError::AccessDenied));
+    }
+}
@@ -7,6 +7,22 @@ pub struct TokenRequest {
     pub client_id: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthorizationCodeTokenRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    pub code: String,
+    pub code_verifier: String,
+    pub redirect_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
     pub access_token: String,
@@ -32,9 +48,24 @@ pub struct DeviceCodeResponse {
     pub verification_uri: String,
     pub verification_uri_complete: String,
     pub expires_in: u64,
+    /// Seconds to wait between token requests, 5 when the server leaves it out
+    #[serde(default = "default_poll_interval")]
     pub interval: u64,
 }
 
+fn default_poll_interval() -> u64 {
+    5
+}
+
+/// Error body of a failed token request (RFC 6749 section 5.2, with the
+/// device flow codes of RFC 8628 section 3.5).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AuthData {
     pub access_token: String,
@@ -44,29 +75,6 @@ pub struct AuthData {
     pub scope: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
-pub struct LoginConfig {
-    pub client_id: String,
-    pub auth_env: AuthEnv,
-    pub scope: AuthScope,
-}
-
-impl Default for LoginConfig {
-    fn default() -> Self {
-        Self {
-            client_id: "37lfJRh96Q9MT86n7MigrFcRLBsNxIXD".to_string(),
-            auth_env: AuthEnv::Dev,
-            scope: AuthScope::Email,
-        }
-    }
-}
-
-impl LoginConfig {
-    pub fn new(client_id: String, auth_env: AuthEnv, scope: AuthScope) -> Self {
-        Self { client_id, auth_env, scope }
-    }
-}
-
 #[derive(
     Debug,
     Clone,
@@ -86,6 +94,30 @@ pub enum AuthScope {
     OfflineAccess,
 }
 
+/// How the user logs in.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    Serialize,
+    Deserialize,
+    Hash,
+    Eq,
+    PartialEq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+    strum_macros::AsRefStr,
+)]
+#[strum(serialize_all = "camelCase")]
+pub enum AuthFlow {
+    /// Enter a code shown by the app on any device (RFC 8628)
+    #[default]
+    DeviceCode,
+    /// Log in in the browser on this machine, which redirects back to a
+    /// loopback listener (RFC 7636, RFC 8252)
+    AuthorizationCode,
+}
+
 #[derive(
     Debug,
     Clone,
@@ -112,6 +144,14 @@ impl AuthEnv {
         }
     }
 
+    /// Client id of the app registered in this environment, if built in
+    pub fn get_client_id(&self) -> Option<&'static str> {
+        match self {
+            AuthEnv::Dev => Some("37lfJRh96Q9MT86n7MigrFcRLBsNxIXD"),
+            AuthEnv::Prod => None,
+        }
+    }
+
     pub fn get_audience(&self) -> &'static str {
         match self {
             AuthEnv::Dev => "openspace-dev.ai",
@@ -119,6 +159,10 @@ impl AuthEnv {
         }
     }
 
+    pub fn get_authorize_url(&self) -> String {
+        format!("{}/authorize", self.get_host())
+    }
+
     pub fn get_auth_url(&self) -> String {
         format!("{}/oauth/device/code", self.get_host())
     }
@@ -0,0 +1,27 @@
+use crate::api::oauth::pkg_auth::DeviceCodeResponse;
+use serde::Serialize;
+
+/// What the user needs to finish a device-code login in their browser.
+/// Payload of `login-device-code`, mirrors
+/// `src/rust-api/model/DeviceCodePrompt.ts`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCodePrompt {
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Verification URL with the user code filled in, for a link or QR code
+    pub verification_uri_complete: String,
+    /// When the code expires, in seconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+impl DeviceCodePrompt {
+    pub fn new(response: &DeviceCodeResponse, now: u64) -> Self {
+        Self {
+            user_code: response.user_code.clone(),
+            verification_uri: response.verification_uri.clone(),
+            verification_uri_complete: response.verification_uri_complete.clone(),
+            expires_at: now + response.expires_in,
+        }
+    }
+}
@@ -1,97 +1,313 @@
 use crate::api::http::client::create_http_client;
+use crate::api::http::retry::{parse_retry_after, with_retry, RetryPolicy};
+use crate::api::oauth::auth::refresh_access_token;
 use crate::api::openspace::pub_user_info::UserInfo;
+use crate::api::openspace::tictac::{
+    CompleteUploadRequest, CompleteUploadResponse, GetOrCreateUploadResponse, TicTacUploadRequest, UploadPartResponse,
+};
 use crate::cache::user_cache::get_user_config;
 use crate::error::AppError;
 use crate::ipc::ipc_error::IpcError;
 use crate::ipc::pub_ipc_response::IpcStatus;
-use reqwest::{Client, Method};
+use crate::traits::traits::ToJson;
+use crate::util::time::unix_now;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_LENGTH};
+use reqwest::{Body, Client, Method};
 use serde_json::{from_value, Value};
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
 
 static USER_AGENT: &str = "ai.openspace.tactic/0.0.1";
+static TICTAC_UPLOADS_PATH: &str = "/api/tictac/uploads";
+/// Hex-encoded SHA-256 of a part body, checked by the server on receipt
+static CHECKSUM_HEADER: &str = "X-Checksum-Sha256";
+static BYTES_REQUEST_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 static API_CLIENT: LazyLock<Client> = LazyLock::new(|| create_http_client());
-static API: LazyLock<Option<OSApi>> = LazyLock::new(|| create_os_api());
+/// Seconds before its expiry that the access token is refreshed
+static REFRESH_MARGIN: u64 = 60;
+/// Replaced on login and whenever the access token is refreshed
+static API: LazyLock<RwLock<Option<Arc<OSApi>>>> = LazyLock::new(|| RwLock::new(create_os_api()));
+/// Held while refreshing, so concurrent requests share one refresh
+static REFRESH_LOCK: Mutex<()> = Mutex::const_new(());
 
 struct OSApi {
     api_host: String,
     access_token: String,
     token_type: String,
+    expires_at: Option<u64>,
 }
 
 impl OSApi {
-    pub fn new(api_host: String, access_token: String, token_type: String) -> Self {
+    pub fn new(
+        api_host: String,
+        access_token: String,
+        token_type: String,
+        expires_at: Option<u64>,
+    ) -> Self {
         Self {
             api_host,
             access_token,
             token_type,
+            expires_at,
         }
     }
 
+    /// Sends a JSON request. Only idempotent requests are retried: one that
+    /// timed out may still have been carried out by the server.
     pub async fn request(
         &self,
         method: &str,
         path: &str,
         body: Value,
         content_type: Option<String>,
+        idempotent: bool,
+    ) -> Result<Value, AppError> {
+        let content_type = content_type.unwrap_or_else(|| "application/json".into());
+        let body = serde_json::to_vec(&body)?;
+        let label = format!("{} {}", method, path);
+        let policy = if idempotent {
+            RetryPolicy::default()
+        } else {
+            RetryPolicy::single_attempt()
+        };
+
+        with_retry(&policy, &label, || {
+            self.send(method, path, body.clone(), &content_type, HeaderMap::new(), None)
+        })
+        .await
+    }
+
+    /// Sends a raw binary body, e.g. a part of a multipart upload.
+    ///
+    /// Unlike `request`, this makes a single attempt: a streamed body can only
+    /// be sent once, so callers retry by rebuilding it.
+    ///
+    /// `content_length` is set explicitly so streamed bodies are not sent
+    /// with chunked transfer encoding. Large bodies get a longer timeout than
+    /// the shared client's default.
+    pub async fn request_bytes(
+        &self,
+        method: &str,
+        path: &str,
+        body: Body,
+        content_length: u64,
+        content_type: &str,
+        mut headers: HeaderMap,
+    ) -> Result<Value, AppError> {
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(content_length));
+        self.send(
+            method,
+            path,
+            body,
+            content_type,
+            headers,
+            Some(BYTES_REQUEST_TIMEOUT),
+        )
+        .await
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: impl Into<Body>,
+        content_type: &str,
+        headers: HeaderMap,
+        timeout: Option<Duration>,
     ) -> Result<Value, AppError> {
         println!("Requesting {} {}", method, path);
         let url = format!("{}{}", self.api_host, path);
         let method = Method::from_bytes(method.as_bytes())
             .map_err(|e| AppError::InvalidArgument(format!("Invalid HTTP method: {}", e)))?;
-        let response = API_CLIENT
-            .request(method, &url)
+        let mut builder = API_CLIENT.request(method, &url).headers(headers);
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        let response = builder
             .header(
                 "Authorization",
                 format!("{} {}", self.token_type, self.access_token),
             )
             .header("User-Agent", USER_AGENT)
-            .header(
-                "Content-Type",
-                content_type.unwrap_or_else(|| "application/json".into()),
-            )
-            .body(serde_json::to_vec(&body)?)
+            .header("Content-Type", content_type)
+            .body(body)
             .send()
             .await?;
 
         let status = response.status();
-        let json = response.json::<Value>().await?;
-        println!("Response: the sauce");
+        // Check the status before parsing: error pages from proxies (e.g. a
+        // 502) are not JSON and must still surface as `ApiRequest`
         if status.as_u16() >= 300 {
             return Err(AppError::ApiRequest {
                 status: status.as_u16(),
                 message: format!("Request failed: {}", status),
+                retry_after: parse_retry_after(response.headers()),
             });
         }
 
-        Ok(json)
+        Ok(response.json::<Value>().await?)
     }
 }
-fn create_os_api() -> Option<OSApi> {
+fn create_os_api() -> Option<Arc<OSApi>> {
     get_user_config().map(|config| {
-        OSApi::new(
+        Arc::new(OSApi::new(
             config.api_config.host().to_string(),
             config.access_token,
             config.token_type,
-        )
+            config.expires_at,
+        ))
     })
 }
 
+/// Reloads the API client from the saved user config, e.g. after a login.
+pub fn reset_api() {
+    *API.write().unwrap() = create_os_api();
+}
+
+fn current_api() -> Result<Arc<OSApi>, AppError> {
+    API.read().unwrap().clone().ok_or(AppError::ApiNotInitialized)
+}
+
+/// The API client, with its access token refreshed first if it is about to
+/// expire.
+async fn api() -> Result<Arc<OSApi>, AppError> {
+    let api = current_api()?;
+    let Some(expires_at) = api.expires_at else {
+        return Ok(api);
+    };
+
+    let now = unix_now();
+    if expires_at <= now + REFRESH_MARGIN {
+        match refresh(&api).await {
+            Ok(refreshed) => return Ok(refreshed),
+            // Still usable for a little while
+            Err(e) if expires_at > now => eprintln!("Failed to refresh access token: {}", e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(api)
+}
+
+/// Refreshes the access token `stale` was sent with, unless a concurrent
+/// request already has, and returns the client with the new token.
+async fn refresh(stale: &OSApi) -> Result<Arc<OSApi>, AppError> {
+    let _refreshing = REFRESH_LOCK.lock().await;
+    let current = current_api()?;
+    if current.access_token != stale.access_token {
+        return Ok(current);
+    }
+
+    refresh_access_token().await?;
+    reset_api();
+    current_api()
+}
+
+/// Sends a request to the API. Requests with an idempotent method are
+/// retried on transient failures; others are sent once.
 pub async fn make_request(
     method: &str,
     path: &str,
     body: Value,
     content_type: Option<String>,
 ) -> Result<Value, AppError> {
-    let res = API
-        .as_ref()
-        .ok_or(AppError::ApiNotInitialized)?
-        .request(method, path, body, content_type)
-        .await?;
+    send_request(method, path, body, content_type, is_idempotent(method)).await
+}
 
-    println!("{}", serde_json::to_string(&res).unwrap());
+/// Methods that have the same effect however many times they are sent.
+fn is_idempotent(method: &str) -> bool {
+    matches!(
+        method.to_ascii_uppercase().as_str(),
+        "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE"
+    )
+}
 
-    Ok(res)
+async fn send_request(
+    method: &str,
+    path: &str,
+    body: Value,
+    content_type: Option<String>,
+    idempotent: bool,
+) -> Result<Value, AppError> {
+    let api = api().await?;
+    match api
+        .request(method, path, body.clone(), content_type.clone(), idempotent)
+        .await
+    {
+        // Revoked or expired early; the server did not act on it, so try once
+        // more with a fresh token
+        Err(AppError::ApiRequest { status: 401, .. }) => {
+            refresh(&api)
+                .await?
+                .request(method, path, body, content_type, idempotent)
+                .await
+        }
+        res => res,
+    }
+}
+
+pub async fn get_or_create_upload(
+    request: &TicTacUploadRequest,
+) -> Result<GetOrCreateUploadResponse, AppError> {
+    // The server returns the existing upload for the same file, so sending
+    // it again is safe
+    let res = send_request("POST", TICTAC_UPLOADS_PATH, request.to_json()?, None, true).await?;
+    from_value(res).map_err(|e| AppError::ApiParseFailed(e.to_string()))
+}
+
+/// Uploads one part. Fails with `TokenRefreshed` if the access token was
+/// rejected and refreshed, as the body cannot be sent twice; callers retry
+/// with a new body, e.g. through `with_retry`.
+pub async fn upload_part(
+    upload_id: &str,
+    part_number: i32,
+    body: Body,
+    content_length: u64,
+    checksum: &str,
+) -> Result<UploadPartResponse, AppError> {
+    let path = format!("{}/{}/parts/{}", TICTAC_UPLOADS_PATH, upload_id, part_number);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        CHECKSUM_HEADER,
+        HeaderValue::from_str(checksum)
+            .map_err(|e| AppError::InvalidArgument(format!("Invalid checksum: {}", e)))?,
+    );
+
+    let api = api().await?;
+    let res = match api
+        .request_bytes(
+            "PUT",
+            &path,
+            body,
+            content_length,
+            "application/octet-stream",
+            headers,
+        )
+        .await
+    {
+        // The body is spent, so the caller has to send the part again
+        Err(AppError::ApiRequest { status: 401, .. }) => {
+            refresh(&api).await?;
+            return Err(AppError::TokenRefreshed);
+        }
+        res => res?,
+    };
+
+    from_value(res).map_err(|e| AppError::ApiParseFailed(e.to_string()))
 }
+
+/// Completes a multipart upload. Sent once: the server is not known to
+/// accept a second completion, so a failure fails the job instead, and the
+/// queue retries it from the pending upload.
+pub async fn complete_upload(
+    upload_id: &str,
+    request: &CompleteUploadRequest,
+) -> Result<CompleteUploadResponse, AppError> {
+    let path = format!("{}/{}/complete", TICTAC_UPLOADS_PATH, upload_id);
+    let res = make_request("POST", &path, request.to_json()?, None).await?;
+    from_value(res).map_err(|e| AppError::ApiParseFailed(e.to_string()))
+}
+
 /// Special case function that returns IpcError to handle 401 as Ok(None).
 ///
 /// This is one of the rare cases where we use IpcError instead of AppError,
@@ -110,8 +326,9 @@ pub async fn get_user_info() -> Result<Option<UserInfo>, IpcError> {
             Ok(Some(user_info))
         }
 
-        Err(AppError::ApiRequest { status, message }) if status == 401 => {
-            // Not authenticated → recoverable, return Ok(None)
+        Err(AppError::ApiRequest { status: 401, .. }) | Err(AppError::NotAuthenticated) => {
+            // Not authenticated, or the token could not be refreshed →
+            // recoverable, return Ok(None)
             Ok(None)
         }
 
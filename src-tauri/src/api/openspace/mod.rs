@@ -1,5 +1,4 @@
-mod tictac;
-
 pub mod api;
 pub mod pub_api_env;
 pub mod pub_user_info;
+pub mod tictac;
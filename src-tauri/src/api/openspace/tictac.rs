@@ -37,3 +37,56 @@ pub struct GetOrCreateUploadResponse {
     pub upload_id: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadPartResponse {
+    #[serde(rename = "partNumber")]
+    pub part_number: i32,
+    #[serde(rename = "etag")]
+    pub etag: String,
+    /// SHA-256 of the bytes the server received, when it reports one
+    #[serde(rename = "checksum", default)]
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedPart {
+    #[serde(rename = "partNumber")]
+    pub part_number: i32,
+    #[serde(rename = "etag")]
+    pub etag: String,
+    #[serde(rename = "checksum")]
+    pub checksum: String,
+}
+
+impl CompletedPart {
+    pub fn new(part_number: i32, etag: String, checksum: String) -> Self {
+        Self {
+            part_number,
+            etag,
+            checksum,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompleteUploadRequest {
+    #[serde(rename = "parts")]
+    pub parts: Vec<CompletedPart>,
+    /// Composite checksum of the parts, see
+    /// `upload::checksum::composite_checksum`
+    #[serde(rename = "checksum")]
+    pub checksum: String,
+}
+
+impl CompleteUploadRequest {
+    pub fn new(parts: Vec<CompletedPart>, checksum: String) -> Self {
+        Self { parts, checksum }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompleteUploadResponse {
+    /// Composite checksum computed by the server from the parts it stored
+    #[serde(rename = "checksum", default)]
+    pub checksum: Option<String>,
+}
@@ -2,6 +2,11 @@ use crate::cache::pkg_file_config::{SkippedFile, SKIPPED_FILES_FILE};
 use crate::cache::root_cache::{clear_cache_file, read_cache_file, write_cache_file};
 use crate::error::AppError;
 use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles on the skipped files list, which
+/// concurrent uploads add to as they finish.
+static SKIPPED_FILES_LOCK: Mutex<()> = Mutex::new(());
 
 pub fn load_skipped_files() -> Option<HashSet<SkippedFile>> {
     read_cache_file(SKIPPED_FILES_FILE)
@@ -17,28 +22,163 @@ pub fn add_skipped_file(
     filename: &str,
     size: i64,
     device_id: &str,
+    partial_hash: &str,
+    content_hash: &str,
 ) -> Result<(), AppError> {
-    let mut skipped = load_skipped_files().unwrap_or(HashSet::new());
+    let _guard = SKIPPED_FILES_LOCK.lock().unwrap();
+    let mut skipped = load_skipped_files().unwrap_or_default();
     skipped.insert(SkippedFile::new(
         filename.to_string(),
         size,
         device_id.to_string(),
+        partial_hash.to_string(),
+        content_hash.to_string(),
     ));
     save_skipped_files(&skipped)?;
 
     Ok(())
 }
 
+/// Moves the entries of device `from` for which `is_on_device` holds, given
+/// their filename and size, over to device `to`. Returns how many entries
+/// moved.
+pub fn rename_device_in_skipped_files(
+    from: &str,
+    to: &str,
+    is_on_device: impl Fn(&str, i64) -> bool,
+) -> Result<usize, AppError> {
+    let _guard = SKIPPED_FILES_LOCK.lock().unwrap();
+    let Some(skipped) = load_skipped_files() else {
+        return Ok(0);
+    };
+
+    let mut renamed = 0;
+    let skipped: HashSet<SkippedFile> = skipped
+        .into_iter()
+        .map(|mut file| {
+            if file.device_id == from && is_on_device(&file.filename, file.size) {
+                file.device_id = to.to_string();
+                renamed += 1;
+            }
+            file
+        })
+        .collect();
+
+    if renamed > 0 {
+        save_skipped_files(&skipped)?;
+    }
+    Ok(renamed)
+}
+
 pub fn clear_skipped_files() -> Result<(), AppError> {
     clear_cache_file(SKIPPED_FILES_FILE)
 }
 
-pub fn is_file_skipped(filename: &str, size: i64, device_id: &str) -> bool {
-    match load_skipped_files() {
-        Some(skipped) => {
-            let file = SkippedFile::new(filename.to_string(), size, device_id.to_string());
-            skipped.contains(&file)
-        }
-        None => false,
+/// Whether a file with the same content was already uploaded.
+///
+/// `partial_hash` is compared first; `content_hash` is only called (it reads
+/// the whole file) when some entry has the same size and partial hash.
+/// Legacy entries keep their old `(filename, size, device_id)` meaning so
+/// nothing uploaded before content hashing is uploaded again, but only until
+/// the device has a hashed entry. Cameras reuse file names once a card is
+/// wiped, so from then on a legacy entry would skip new photos; a file it
+/// did cover is recognized by the server instead. Legacy entries are not
+/// rewritten with the hashes of the file that matched, which may not be the
+/// one that was uploaded; hashes are only recorded for uploads the server
+/// has confirmed.
+pub fn is_file_skipped(
+    filename: &str,
+    size: i64,
+    device_id: &str,
+    partial_hash: &str,
+    content_hash: impl FnOnce() -> Result<String, AppError>,
+) -> Result<bool, AppError> {
+    let Some(skipped) = load_skipped_files() else {
+        return Ok(false);
+    };
+    matches_skipped(
+        &skipped,
+        filename,
+        size,
+        device_id,
+        partial_hash,
+        content_hash,
+    )
+}
+
+fn matches_skipped(
+    skipped: &HashSet<SkippedFile>,
+    filename: &str,
+    size: i64,
+    device_id: &str,
+    partial_hash: &str,
+    content_hash: impl FnOnce() -> Result<String, AppError>,
+) -> Result<bool, AppError> {
+    let device_hashed = skipped
+        .iter()
+        .any(|f| f.device_id == device_id && !f.is_legacy());
+    let legacy = SkippedFile::legacy(filename.to_string(), size, device_id.to_string());
+    if !device_hashed && skipped.contains(&legacy) {
+        return Ok(true);
+    }
+
+    let collides = skipped
+        .iter()
+        .any(|f| f.size == size && f.partial_hash.as_deref() == Some(partial_hash));
+    if !collides {
+        return Ok(false);
+    }
+
+    let content_hash = content_hash()?;
+    Ok(skipped.iter().any(|f| {
+        !f.is_legacy()
+            && f.size == size
+            && f.partial_hash.as_deref() == Some(partial_hash)
+            && f.content_hash.as_deref() == Some(content_hash.as_str())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashed(filename: &str, device_id: &str, hash: &str) -> SkippedFile {
+        SkippedFile::new(
+            filename.to_string(),
+            10,
+            device_id.to_string(),
+            hash.to_string(),
+            hash.to_string(),
+        )
+    }
+
+    fn legacy(filename: &str, device_id: &str) -> SkippedFile {
+        SkippedFile::legacy(filename.to_string(), 10, device_id.to_string())
+    }
+
+    fn is_skipped(skipped: &HashSet<SkippedFile>, filename: &str, hash: &str) -> bool {
+        matches_skipped(skipped, filename, 10, "cam", hash, || Ok(hash.to_string())).unwrap()
+    }
+
+    #[test]
+    fn legacy_entries_match_until_the_device_is_hashed() {
+        let mut skipped = HashSet::from([legacy("R001.JPG", "cam")]);
+        assert!(is_skipped(&skipped, "R001.JPG", "new"));
+
+        skipped.insert(hashed("R002.JPG", "cam", "other"));
+        assert!(!is_skipped(&skipped, "R001.JPG", "new"));
+    }
+
+    #[test]
+    fn hashes_of_other_devices_keep_legacy_entries() {
+        let skipped = HashSet::from([legacy("R001.JPG", "cam"), hashed("R002.JPG", "other", "x")]);
+        assert!(is_skipped(&skipped, "R001.JPG", "new"));
+    }
+
+    #[test]
+    fn hashed_entries_match_by_content() {
+        let skipped = HashSet::from([hashed("R001.JPG", "cam", "same")]);
+        assert!(is_skipped(&skipped, "RENAMED.JPG", "same"));
+        assert!(!is_skipped(&skipped, "R001.JPG", "changed"));
     }
 }
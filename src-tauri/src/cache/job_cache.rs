@@ -0,0 +1,184 @@
+use crate::cache::pub_job_config::{JobState, UploadJob, UPLOAD_JOBS_FILE};
+use crate::cache::root_cache::{clear_cache_file, read_cache_file_or_set_aside, write_cache_file};
+use crate::error::AppError;
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles on the jobs file, which the queue
+/// worker and the job commands update concurrently.
+static UPLOAD_JOBS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Jobs in the order they were first queued.
+fn load_upload_jobs() -> Result<Vec<UploadJob>, AppError> {
+    Ok(read_cache_file_or_set_aside(UPLOAD_JOBS_FILE)?.unwrap_or_default())
+}
+
+fn save_upload_jobs(jobs: &[UploadJob]) -> Result<(), AppError> {
+    write_cache_file(UPLOAD_JOBS_FILE, &jobs)
+}
+
+/// Puts jobs in queue order: highest priority first, then oldest first.
+fn sort_queue(jobs: &mut [UploadJob]) {
+    // Stable, so jobs of equal priority keep the order they were queued in
+    jobs.sort_by_key(|job| std::cmp::Reverse(job.priority));
+}
+
+/// Returns every job in queue order.
+pub fn get_upload_jobs() -> Result<Vec<UploadJob>, AppError> {
+    let mut jobs = load_upload_jobs()?;
+    sort_queue(&mut jobs);
+    Ok(jobs)
+}
+
+/// Adds jobs to the queue and returns them as stored.
+///
+/// A file that is already queued or running keeps its existing job. A file
+/// whose job has finished is queued again on the same job.
+pub fn enqueue_upload_jobs(new_jobs: Vec<UploadJob>) -> Result<Vec<UploadJob>, AppError> {
+    let _guard = UPLOAD_JOBS_LOCK.lock().unwrap();
+    let mut jobs = load_upload_jobs()?;
+    let mut queued = Vec::with_capacity(new_jobs.len());
+
+    for new_job in new_jobs {
+        match jobs.iter_mut().find(|job| job.is_same_file(&new_job)) {
+            Some(job) if job.state.is_active() => queued.push(job.clone()),
+            Some(job) => {
+                job.state = JobState::Queued;
+                job.size = new_job.size;
+                job.attempts = 0;
+                job.last_error = None;
+                job.not_before = None;
+                job.updated_at = new_job.updated_at;
+                queued.push(job.clone());
+            }
+            None => {
+                queued.push(new_job.clone());
+                jobs.push(new_job);
+            }
+        }
+    }
+
+    save_upload_jobs(&jobs)?;
+    Ok(queued)
+}
+
+/// Marks the next queued job that is due at `now` as running and returns it.
+pub fn claim_next_upload_job(now: u64) -> Result<Option<UploadJob>, AppError> {
+    let _guard = UPLOAD_JOBS_LOCK.lock().unwrap();
+    let mut jobs = load_upload_jobs()?;
+
+    // Same pick as the head of `sort_queue`, without reordering the file
+    let Some(job) = jobs
+        .iter_mut()
+        .enumerate()
+        .filter(|(_, job)| job.is_due(now))
+        .max_by_key(|(index, job)| (job.priority, std::cmp::Reverse(*index)))
+        .map(|(_, job)| job)
+    else {
+        return Ok(None);
+    };
+    job.state = JobState::Running;
+    job.attempts += 1;
+    job.not_before = None;
+    job.updated_at = now;
+    let job = job.clone();
+
+    save_upload_jobs(&jobs)?;
+    Ok(Some(job))
+}
+
+/// Earliest time a queued job that is not due yet may start, if any.
+pub fn next_upload_job_due() -> Result<Option<u64>, AppError> {
+    let jobs = load_upload_jobs()?;
+    Ok(jobs
+        .iter()
+        .filter(|job| job.state == JobState::Queued)
+        .filter_map(|job| job.not_before)
+        .min())
+}
+
+/// Applies `update` to the job with `id` and saves it. Nothing is saved if
+/// `update` fails.
+pub fn update_upload_job(
+    id: &str,
+    update: impl FnOnce(&mut UploadJob) -> Result<(), AppError>,
+) -> Result<UploadJob, AppError> {
+    let _guard = UPLOAD_JOBS_LOCK.lock().unwrap();
+    let mut jobs = load_upload_jobs()?;
+    let job = jobs
+        .iter_mut()
+        .find(|job| job.id == id)
+        .ok_or_else(|| AppError::InvalidArgument(format!("No upload job with id {}", id)))?;
+
+    update(job)?;
+    let job = job.clone();
+
+    save_upload_jobs(&jobs)?;
+    Ok(job)
+}
+
+/// Removes a job that is not running.
+pub fn remove_upload_job(id: &str) -> Result<(), AppError> {
+    let _guard = UPLOAD_JOBS_LOCK.lock().unwrap();
+    let mut jobs = load_upload_jobs()?;
+    let index = jobs
+        .iter()
+        .position(|job| job.id == id)
+        .ok_or_else(|| AppError::InvalidArgument(format!("No upload job with id {}", id)))?;
+
+    if jobs[index].state == JobState::Running {
+        return Err(AppError::Conflict(format!(
+            "{} is uploading; cancel it before removing it",
+            jobs[index].filename
+        )));
+    }
+
+    jobs.remove(index);
+    save_upload_jobs(&jobs)
+}
+
+/// Moves every job in `from` to `to`. Returns how many jobs changed.
+pub fn transition_upload_jobs(from: JobState, to: JobState, now: u64) -> Result<usize, AppError> {
+    let _guard = UPLOAD_JOBS_LOCK.lock().unwrap();
+    let mut jobs = load_upload_jobs()?;
+    let mut changed = 0;
+
+    for job in jobs.iter_mut().filter(|job| job.state == from) {
+        job.state = to;
+        job.updated_at = now;
+        changed += 1;
+    }
+
+    if changed > 0 {
+        save_upload_jobs(&jobs)?;
+    }
+    Ok(changed)
+}
+
+/// Moves the jobs of device `from` for which `is_on_device` holds, given
+/// their filename and size, over to device `to`. Returns how many jobs moved.
+pub fn rename_device_in_upload_jobs(
+    from: &str,
+    to: &str,
+    is_on_device: impl Fn(&str, i64) -> bool,
+) -> Result<usize, AppError> {
+    let _guard = UPLOAD_JOBS_LOCK.lock().unwrap();
+    let mut jobs = load_upload_jobs()?;
+    let mut renamed = 0;
+
+    for job in jobs
+        .iter_mut()
+        .filter(|job| job.device_id == from && is_on_device(&job.filename, job.size as i64))
+    {
+        job.device_id = to.to_string();
+        renamed += 1;
+    }
+
+    if renamed > 0 {
+        save_upload_jobs(&jobs)?;
+    }
+    Ok(renamed)
+}
+
+pub fn clear_upload_jobs() -> Result<(), AppError> {
+    clear_cache_file(UPLOAD_JOBS_FILE)
+}
@@ -1,8 +1,14 @@
 mod pkg_file_config;
+pub mod pkg_upload_config;
 
 pub mod root_cache;
 pub mod pub_user_config;
 pub mod pub_oauth_config;
+pub mod pub_settings_config;
+pub mod pub_job_config;
 pub mod file_cache;
 pub mod oauth_cache;
-pub mod user_cache;
\ No newline at end of file
+pub mod user_cache;
+pub mod settings_cache;
+pub mod upload_cache;
+pub mod job_cache;
@@ -1,10 +1,21 @@
 use crate::cache::pub_oauth_config::{OAuthConfig, OAUTH_CONFIG_FILE};
 use crate::cache::root_cache;
+use crate::error::AppError;
 
-pub fn get_oauth_config() -> Option<OAuthConfig> {
-    root_cache::read_cache_file(OAUTH_CONFIG_FILE)
+/// Returns the saved OAuth config, or the built-in defaults if none was
+/// saved yet. A config that cannot be read is an error rather than silently
+/// replaced by the defaults, which may be for another environment.
+pub fn get_oauth_config() -> Result<OAuthConfig, AppError> {
+    match root_cache::try_read_cache_file(OAUTH_CONFIG_FILE) {
+        Ok(config) => Ok(config.unwrap_or_default()),
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(e)
+        }
+    }
 }
 
-pub fn save_auth_data(auth_data: &OAuthConfig) {
-    root_cache::write_cache_file(OAUTH_CONFIG_FILE, auth_data).expect("Error Saving Auth Data");
+pub fn save_oauth_config(config: &OAuthConfig) -> Result<(), AppError> {
+    config.validate()?;
+    root_cache::write_cache_file(OAUTH_CONFIG_FILE, config)
 }
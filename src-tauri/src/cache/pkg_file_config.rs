@@ -2,19 +2,52 @@ use serde::{Deserialize, Serialize};
 
 pub const SKIPPED_FILES_FILE: &str = "skipped_files.json";
 
+/// A file that was already uploaded and should not be uploaded again.
+///
+/// Files are identified by content: `partial_hash` is a cheap fingerprint
+/// compared first, and `content_hash` confirms a match when fingerprints
+/// collide. Entries written before content hashing have neither and are
+/// matched on `(filename, size, device_id)`.
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct SkippedFile {
     pub filename: String,
     pub size: i64,
     pub device_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
 }
 
 impl SkippedFile {
-    pub fn new(filename: String, size: i64, device_id: String) -> Self {
+    pub fn new(
+        filename: String,
+        size: i64,
+        device_id: String,
+        partial_hash: String,
+        content_hash: String,
+    ) -> Self {
         Self {
             filename,
             size,
             device_id,
+            partial_hash: Some(partial_hash),
+            content_hash: Some(content_hash),
         }
     }
+
+    /// An entry as written before content hashing existed.
+    pub fn legacy(filename: String, size: i64, device_id: String) -> Self {
+        Self {
+            filename,
+            size,
+            device_id,
+            partial_hash: None,
+            content_hash: None,
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.partial_hash.is_none() || self.content_hash.is_none()
+    }
 }
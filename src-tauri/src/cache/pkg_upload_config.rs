@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const PENDING_UPLOADS_FILE: &str = "pending_uploads.json";
+pub const UPLOAD_HISTORY_FILE: &str = "upload_history.json";
+pub const DELETION_LOG_FILE: &str = "deletion_log.json";
+
+/// Progress of a multipart upload that has not been finalized yet.
+///
+/// Saved after every part so an interrupted upload can pick up from the
+/// first part that is missing from `completed_parts`.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PendingUpload {
+    pub upload_id: String,
+    pub device_id: String,
+    pub filename: String,
+    pub size: i64,
+    pub part_size: u64,
+    pub num_parts: i32,
+    /// ETags of the parts the server has acknowledged, keyed by part number
+    pub completed_parts: BTreeMap<i32, String>,
+    /// Hex SHA-256 of each acknowledged part, keyed by part number
+    #[serde(default)]
+    pub part_checksums: BTreeMap<i32, String>,
+}
+
+impl PendingUpload {
+    pub fn new(
+        upload_id: String,
+        device_id: String,
+        filename: String,
+        size: i64,
+        part_size: u64,
+        num_parts: i32,
+    ) -> Self {
+        Self {
+            upload_id,
+            device_id,
+            filename,
+            size,
+            part_size,
+            num_parts,
+            completed_parts: BTreeMap::new(),
+            part_checksums: BTreeMap::new(),
+        }
+    }
+
+    pub fn key(&self) -> String {
+        pending_upload_key(&self.device_id, &self.filename, self.size)
+    }
+
+    /// A part only counts as done once both its ETag and checksum are known.
+    /// Parts saved before checksums were tracked are uploaded again.
+    pub fn is_part_completed(&self, part_number: i32) -> bool {
+        self.completed_parts.contains_key(&part_number)
+            && self.part_checksums.contains_key(&part_number)
+    }
+
+    pub fn complete_part(&mut self, part_number: i32, etag: String, checksum: String) {
+        self.completed_parts.insert(part_number, etag);
+        self.part_checksums.insert(part_number, checksum);
+    }
+}
+
+pub fn pending_upload_key(device_id: &str, filename: &str, size: i64) -> String {
+    format!("{}:{}:{}", device_id, filename, size)
+}
+
+/// A finalized upload, kept as a local record of what was sent.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadRecord {
+    pub upload_id: String,
+    pub device_id: String,
+    pub filename: String,
+    pub size: i64,
+    /// Composite checksum sent with the finalize call, see
+    /// `upload::checksum::composite_checksum`
+    pub checksum: String,
+    /// Whether the server reported the same checksum back
+    pub verified: bool,
+    /// Seconds since the Unix epoch
+    pub completed_at: u64,
+}
+
+/// Audit entry for a file deleted from a camera after its upload.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionRecord {
+    pub upload_id: String,
+    pub device_id: String,
+    pub filename: String,
+    pub size: i64,
+    /// Checksum the server confirmed before the file was deleted
+    pub checksum: String,
+    /// The deletion was only logged, the file is still on the camera
+    pub dry_run: bool,
+    /// Seconds since the Unix epoch
+    pub deleted_at: u64,
+}
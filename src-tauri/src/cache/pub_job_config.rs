@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+pub const UPLOAD_JOBS_FILE: &str = "upload_jobs.json";
+
+/// Where a job is in the queue.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    /// Waiting to be picked up by the queue worker
+    Queued,
+    /// Being uploaded. Jobs left running when the app quit are queued again
+    /// on the next start.
+    Running,
+    Completed,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// Whether the job still has work to do.
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+/// One file waiting for, or done with, its upload. Persisted so queued work
+/// survives the app being closed.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadJob {
+    pub id: String,
+    pub device_id: String,
+    /// Path of the file on the camera, as shown in the UI
+    pub filename: String,
+    pub size: u64,
+    pub state: JobState,
+    /// Jobs with a higher priority are uploaded first; equal priorities go
+    /// in the order they were queued
+    pub priority: i32,
+    /// Number of times the worker has started this job
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Seconds since the Unix epoch before which a job queued again after a
+    /// failure is not started
+    #[serde(default)]
+    pub not_before: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Seconds since the Unix epoch
+    pub updated_at: u64,
+}
+
+impl UploadJob {
+    pub fn new(
+        id: String,
+        device_id: String,
+        filename: String,
+        size: u64,
+        now: u64,
+    ) -> Self {
+        Self {
+            id,
+            device_id,
+            filename,
+            size,
+            state: JobState::Queued,
+            priority: 0,
+            attempts: 0,
+            last_error: None,
+            not_before: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Whether the job is queued and may start at `now`.
+    pub fn is_due(&self, now: u64) -> bool {
+        self.state == JobState::Queued && self.not_before.is_none_or(|not_before| not_before <= now)
+    }
+
+    /// Whether both jobs are for the same file of the same camera.
+    pub fn is_same_file(&self, other: &UploadJob) -> bool {
+        self.device_id == other.device_id
+            && self.filename == other.filename
+            && self.size == other.size
+    }
+}
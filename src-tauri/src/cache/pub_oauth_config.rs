@@ -1,11 +1,169 @@
-use serde::{Deserialize, Serialize};
-use crate::api::oauth::pkg_auth::{AuthEnv, AuthScope};
+use crate::api::oauth::pkg_auth::{AuthEnv, AuthFlow, AuthScope};
+use crate::error::AppError;
+use serde::{Deserialize, Deserializer, Serialize};
 
 pub const OAUTH_CONFIG_FILE: &str = "oauth_config.json";
 
+/// How the app logs in. Serialized to match
+/// `src/rust-api/model/OAuthConfig.ts`.
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct OAuthConfig {
-    pub client_id: String,
+    /// Client id of the app; the one built in for `env` if unset
+    #[serde(default)]
+    pub client_id: Option<String>,
     pub env: AuthEnv,
-    pub scope: AuthScope,
+    /// Scopes asked for at login. `offline_access` is always added
+    #[serde(
+        alias = "scope",
+        deserialize_with = "one_or_many",
+        default = "default_scopes"
+    )]
+    pub scopes: Vec<AuthScope>,
+    #[serde(default)]
+    pub flow: AuthFlow,
+    /// Port the authorization-code flow listens on for the redirect. Must
+    /// match a callback URL allowed for the client; any free port if unset
+    #[serde(default)]
+    pub redirect_port: Option<u16>,
+}
+
+impl Default for OAuthConfig {
+    fn default() -> Self {
+        Self {
+            client_id: None,
+            env: AuthEnv::Prod,
+            scopes: default_scopes(),
+            flow: AuthFlow::default(),
+            redirect_port: None,
+        }
+    }
+}
+
+impl OAuthConfig {
+    /// The configured client id, else the one built in for the environment.
+    /// Fails with `LoginNotConfigured` rather than fall back to another
+    /// environment.
+    pub fn client_id(&self) -> Result<&str, AppError> {
+        self.client_id
+            .as_deref()
+            .or(self.env.get_client_id())
+            .ok_or_else(|| AppError::LoginNotConfigured(self.env.to_string()))
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        let client_id = self.client_id()?;
+        if client_id.is_empty() || client_id.contains(char::is_whitespace) {
+            return Err(AppError::InvalidArgument(format!(
+                "Invalid client id: '{}'",
+                client_id
+            )));
+        }
+        if self.scopes.is_empty() {
+            return Err(AppError::InvalidArgument(
+                "At least one scope is required".to_string(),
+            ));
+        }
+        // Privileged ports cannot be bound without elevated rights
+        if self.redirect_port.is_some_and(|port| port < 1024) {
+            return Err(AppError::InvalidArgument(
+                "redirect_port must be between 1024 and 65535".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_scopes() -> Vec<AuthScope> {
+    vec![AuthScope::Email]
+}
+
+/// Reads `scopes` as a list, or as the single `scope` of older configs.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<AuthScope>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(AuthScope),
+        Many(Vec<AuthScope>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(scope) => vec![scope],
+        OneOrMany::Many(scopes) => scopes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(client_id: &str) -> OAuthConfig {
+        OAuthConfig {
+            client_id: Some(client_id.to_string()),
+            ..OAuthConfig::default()
+        }
+    }
+
+    #[test]
+    fn reads_a_list_of_scopes() {
+        let config: OAuthConfig =
+            serde_json::from_str(r#"{"env": "Dev", "scopes": ["Openid", "Email"]}"#).unwrap();
+        assert_eq!(config.scopes, vec![AuthScope::Openid, AuthScope::Email]);
+    }
+
+    #[test]
+    fn reads_the_single_scope_of_older_configs() {
+        let config: OAuthConfig =
+            serde_json::from_str(r#"{"env": "Dev", "scope": "Openid"}"#).unwrap();
+        assert_eq!(config.scopes, vec![AuthScope::Openid]);
+    }
+
+    #[test]
+    fn defaults_missing_fields() {
+        let config: OAuthConfig = serde_json::from_str(r#"{"env": "Prod"}"#).unwrap();
+        assert_eq!(config, OAuthConfig::default());
+    }
+
+    #[test]
+    fn default_login_needs_a_client_id() {
+        let default = OAuthConfig::default();
+        assert_eq!(default.env, AuthEnv::Prod);
+        assert!(matches!(
+            default.client_id(),
+            Err(AppError::LoginNotConfigured(_))
+        ));
+        assert_eq!(config("abc123").client_id().unwrap(), "abc123");
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        let config = OAuthConfig {
+            redirect_port: Some(8765),
+            ..config("abc123")
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_client_ids() {
+        assert!(config("").validate().is_err());
+        assert!(config("abc 123").validate().is_err());
+    }
+
+    #[test]
+    fn rejects_empty_scopes() {
+        let config = OAuthConfig {
+            scopes: Vec::new(),
+            ..config("abc123")
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_privileged_redirect_ports() {
+        let config = OAuthConfig {
+            redirect_port: Some(80),
+            ..config("abc123")
+        };
+        assert!(config.validate().is_err());
+    }
 }
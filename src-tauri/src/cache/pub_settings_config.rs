@@ -0,0 +1,56 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Upper bound for both concurrency limits, to keep memory use sane:
+/// every part in flight holds one part buffer.
+pub const MAX_CONCURRENCY: usize = 16;
+
+/// User-editable application settings.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Number of files uploaded at the same time
+    pub max_concurrent_files: usize,
+    /// Number of parts in flight at the same time, across all files of a batch
+    pub max_concurrent_parts: usize,
+    /// Delete each file from the camera once its upload is finalized and the
+    /// server has confirmed its checksum
+    pub delete_after_upload: bool,
+    /// Only log the deletions `delete_after_upload` would make
+    pub deletion_dry_run: bool,
+    /// Copy each file off the camera into the cache before uploading it, for
+    /// cameras whose backend is slow or cannot read part of a file
+    pub stage_files: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_files: 2,
+            max_concurrent_parts: 4,
+            delete_after_upload: false,
+            deletion_dry_run: false,
+            stage_files: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let limits = [
+            ("maxConcurrentFiles", self.max_concurrent_files),
+            ("maxConcurrentParts", self.max_concurrent_parts),
+        ];
+        for (name, value) in limits {
+            if value == 0 || value > MAX_CONCURRENCY {
+                return Err(AppError::InvalidArgument(format!(
+                    "{} must be between 1 and {}",
+                    name, MAX_CONCURRENCY
+                )));
+            }
+        }
+        Ok(())
+    }
+}
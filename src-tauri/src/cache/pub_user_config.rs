@@ -7,6 +7,12 @@ pub const USER_CONFIG_FILE: &str = "user_config.json";
 pub struct UserConfig {
     pub access_token: String,
     pub token_type: String,
+    /// Exchanged for a new access token when this one expires
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// When the access token expires, in seconds since the Unix epoch
+    #[serde(default)]
+    pub expires_at: Option<u64>,
     pub api_config: ApiConfig,
 }
 
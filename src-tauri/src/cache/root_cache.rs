@@ -1,7 +1,7 @@
 use crate::error::AppError;
 use std::fs;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
 pub const STORAGE_DIR: &str = ".openspace_sync";
@@ -38,16 +38,87 @@ pub fn read_cache_file<T: serde::de::DeserializeOwned>(rel_path: &str) -> Option
     serde_json::from_reader(content).ok()?
 }
 
+/// Like `read_cache_file`, but tells a missing file (`Ok(None)`) apart from
+/// one that cannot be read or parsed.
+pub fn try_read_cache_file<T: serde::de::DeserializeOwned>(
+    rel_path: &str,
+) -> Result<Option<T>, AppError> {
+    let Some(path) = get_cache_file(rel_path) else {
+        return Ok(None);
+    };
+    let cache_read = |source: std::io::Error| AppError::CacheRead {
+        file: rel_path.to_string(),
+        source,
+    };
+
+    let content = File::open(path).map_err(cache_read)?;
+    serde_json::from_reader(content)
+        .map(Some)
+        .map_err(|e| cache_read(e.into()))
+}
+
+/// Like `try_read_cache_file`, but a file that cannot be parsed is moved
+/// aside to `<file>.corrupt` and read as missing. Saving then starts a new
+/// file rather than overwriting it, and the old one is kept for recovery.
+/// A file that cannot be read at all is still an error.
+pub fn read_cache_file_or_set_aside<T: serde::de::DeserializeOwned>(
+    rel_path: &str,
+) -> Result<Option<T>, AppError> {
+    read_or_set_aside(&STORAGE_PATH.join(rel_path), rel_path)
+}
+
+fn read_or_set_aside<T: serde::de::DeserializeOwned>(
+    path: &Path,
+    rel_path: &str,
+) -> Result<Option<T>, AppError> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(AppError::CacheRead {
+                file: rel_path.to_string(),
+                source: e,
+            })
+        }
+    };
+
+    match serde_json::from_slice(&content) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) => {
+            let mut aside = path.as_os_str().to_owned();
+            aside.push(".corrupt");
+            let aside = PathBuf::from(aside);
+            eprintln!(
+                "Cache file {} is corrupt ({}), moving it to {}",
+                rel_path,
+                e,
+                aside.display()
+            );
+            fs::rename(path, &aside).map_err(|e| AppError::CacheWrite {
+                file: rel_path.to_string(),
+                source: e,
+            })?;
+            Ok(None)
+        }
+    }
+}
+
 pub fn write_cache_file<T: serde::Serialize>(
     rel_path: &str,
     data: &T,
 ) -> Result<(), AppError> {
     let path: PathBuf = STORAGE_PATH.join(rel_path);
     let content = serde_json::to_string_pretty(data).map_err(AppError::JsonSerialization)?;
-    fs::write(&path, content).map_err(|e| AppError::CacheWrite {
-        file: rel_path.to_string(),
-        source: e,
-    })?;
+
+    // Write to a sibling file and rename it into place so a crash mid-write
+    // never leaves a truncated cache file behind
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)
+        .and_then(|_| fs::rename(&tmp_path, &path))
+        .map_err(|e| AppError::CacheWrite {
+            file: rel_path.to_string(),
+            source: e,
+        })?;
     Ok(())
 }
 
@@ -81,3 +152,38 @@ pub fn clear_all_cache() -> Result<(), AppError> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupt_files_are_set_aside() {
+        let dir = std::env::temp_dir().join(format!("altoid-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jobs.json");
+        fs::write(&path, b"[1, 2,").unwrap();
+
+        let read: Option<Vec<u32>> = read_or_set_aside(&path, "jobs.json").unwrap();
+
+        assert_eq!(read, None);
+        assert!(!path.exists());
+        assert_eq!(fs::read(dir.join("jobs.json.corrupt")).unwrap(), b"[1, 2,");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_and_valid_files_are_read() {
+        let dir = std::env::temp_dir().join(format!("altoid-cache-ok-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jobs.json");
+
+        let missing: Option<Vec<u32>> = read_or_set_aside(&path, "jobs.json").unwrap();
+        assert_eq!(missing, None);
+
+        fs::write(&path, b"[1, 2]").unwrap();
+        let read: Option<Vec<u32>> = read_or_set_aside(&path, "jobs.json").unwrap();
+        assert_eq!(read, Some(vec![1, 2]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
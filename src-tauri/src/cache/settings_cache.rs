@@ -0,0 +1,13 @@
+use crate::cache::pub_settings_config::{Settings, SETTINGS_FILE};
+use crate::cache::root_cache;
+use crate::error::AppError;
+
+/// Returns the saved settings, or the defaults if none were saved yet.
+pub fn get_settings() -> Settings {
+    root_cache::read_cache_file(SETTINGS_FILE).unwrap_or_default()
+}
+
+pub fn save_settings(settings: &Settings) -> Result<(), AppError> {
+    settings.validate()?;
+    root_cache::write_cache_file(SETTINGS_FILE, settings)
+}
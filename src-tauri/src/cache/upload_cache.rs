@@ -0,0 +1,109 @@
+use crate::cache::pkg_upload_config::{
+    pending_upload_key, DeletionRecord, PendingUpload, UploadRecord, DELETION_LOG_FILE,
+    PENDING_UPLOADS_FILE, UPLOAD_HISTORY_FILE,
+};
+use crate::cache::root_cache::{
+    clear_cache_file, read_cache_file, read_cache_file_or_set_aside, write_cache_file,
+};
+use crate::error::AppError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles on the pending uploads file, which
+/// concurrent uploads update after every part.
+static PENDING_UPLOADS_LOCK: Mutex<()> = Mutex::new(());
+static UPLOAD_HISTORY_LOCK: Mutex<()> = Mutex::new(());
+static DELETION_LOG_LOCK: Mutex<()> = Mutex::new(());
+
+fn load_pending_uploads() -> Result<HashMap<String, PendingUpload>, AppError> {
+    Ok(read_cache_file_or_set_aside(PENDING_UPLOADS_FILE)?.unwrap_or_default())
+}
+
+pub fn get_pending_upload(
+    device_id: &str,
+    filename: &str,
+    size: i64,
+) -> Result<Option<PendingUpload>, AppError> {
+    Ok(load_pending_uploads()?.remove(&pending_upload_key(device_id, filename, size)))
+}
+
+pub fn save_pending_upload(upload: &PendingUpload) -> Result<(), AppError> {
+    let _guard = PENDING_UPLOADS_LOCK.lock().unwrap();
+    let mut uploads = load_pending_uploads()?;
+    uploads.insert(upload.key(), upload.clone());
+    write_cache_file(PENDING_UPLOADS_FILE, &uploads)
+}
+
+pub fn remove_pending_upload(upload: &PendingUpload) -> Result<(), AppError> {
+    let _guard = PENDING_UPLOADS_LOCK.lock().unwrap();
+    let mut uploads = load_pending_uploads()?;
+    if uploads.remove(&upload.key()).is_some() {
+        write_cache_file(PENDING_UPLOADS_FILE, &uploads)?;
+    }
+    Ok(())
+}
+
+/// Moves the pending uploads of device `from` for which `is_on_device`
+/// holds, given their filename and size, over to device `to`. Returns how
+/// many moved.
+pub fn rename_device_in_pending_uploads(
+    from: &str,
+    to: &str,
+    is_on_device: impl Fn(&str, i64) -> bool,
+) -> Result<usize, AppError> {
+    let _guard = PENDING_UPLOADS_LOCK.lock().unwrap();
+    let uploads = load_pending_uploads()?;
+    let moves = |upload: &PendingUpload| {
+        upload.device_id == from && is_on_device(&upload.filename, upload.size)
+    };
+    let renamed = uploads.values().filter(|upload| moves(upload)).count();
+    if renamed == 0 {
+        return Ok(0);
+    }
+
+    let uploads: HashMap<String, PendingUpload> = uploads
+        .into_values()
+        .map(|mut upload| {
+            if moves(&upload) {
+                upload.device_id = to.to_string();
+            }
+            (upload.key(), upload)
+        })
+        .collect();
+    write_cache_file(PENDING_UPLOADS_FILE, &uploads)?;
+    Ok(renamed)
+}
+
+pub fn clear_pending_uploads() -> Result<(), AppError> {
+    clear_cache_file(PENDING_UPLOADS_FILE)
+}
+
+pub fn get_upload_history() -> Vec<UploadRecord> {
+    read_cache_file(UPLOAD_HISTORY_FILE).unwrap_or_default()
+}
+
+pub fn add_upload_record(record: UploadRecord) -> Result<(), AppError> {
+    let _guard = UPLOAD_HISTORY_LOCK.lock().unwrap();
+    let mut history: Vec<UploadRecord> =
+        read_cache_file_or_set_aside(UPLOAD_HISTORY_FILE)?.unwrap_or_default();
+    history.push(record);
+    write_cache_file(UPLOAD_HISTORY_FILE, &history)
+}
+
+pub fn clear_upload_history() -> Result<(), AppError> {
+    clear_cache_file(UPLOAD_HISTORY_FILE)
+}
+
+/// The deletion log is an audit trail and is deliberately left alone by
+/// `clear_cache`.
+pub fn get_deletion_log() -> Vec<DeletionRecord> {
+    read_cache_file(DELETION_LOG_FILE).unwrap_or_default()
+}
+
+pub fn add_deletion_record(record: DeletionRecord) -> Result<(), AppError> {
+    let _guard = DELETION_LOG_LOCK.lock().unwrap();
+    let mut log: Vec<DeletionRecord> =
+        read_cache_file_or_set_aside(DELETION_LOG_FILE)?.unwrap_or_default();
+    log.push(record);
+    write_cache_file(DELETION_LOG_FILE, &log)
+}
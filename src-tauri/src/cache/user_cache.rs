@@ -1,21 +1,31 @@
+use crate::api::oauth::pkg_auth::TokenResponse;
 use crate::cache::pub_user_config::{UserConfig, USER_CONFIG_FILE};
 use crate::cache::root_cache;
 use crate::error::AppError;
+use crate::util::time::unix_now;
 
 pub fn get_user_config() -> Option<UserConfig> {
     root_cache::read_cache_file(USER_CONFIG_FILE)
 }
 
-pub fn save_user_config(access_token: String, token_type: String) -> Result<(), AppError> {
+/// Saves the tokens of a login or refresh. Refresh responses may leave out
+/// the refresh token, in which case the saved one is kept.
+pub fn save_user_config(token: &TokenResponse) -> Result<UserConfig, AppError> {
+    let previous = get_user_config();
     let auth_data = UserConfig {
-        access_token,
-        token_type,
+        access_token: token.access_token.clone(),
+        token_type: token.token_type.clone(),
+        refresh_token: token
+            .refresh_token
+            .clone()
+            .or_else(|| previous.as_ref().and_then(|config| config.refresh_token.clone())),
+        expires_at: Some(unix_now() + token.expires_in),
         // TODO load API config
-        api_config: Default::default(),
+        api_config: previous.map(|config| config.api_config).unwrap_or_default(),
     };
 
     root_cache::write_cache_file(USER_CONFIG_FILE, &auth_data)?;
-    Ok(())
+    Ok(auth_data)
 }
 
 pub fn clear_user_config() -> Result<(), AppError> {
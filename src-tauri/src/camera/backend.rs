@@ -0,0 +1,75 @@
+use crate::camera::pub_device_identity::DeviceIdentity;
+use crate::camera::pub_file_info::FileInfo;
+use crate::camera::usb::UsbDevice;
+use crate::error::AppError;
+use std::fmt;
+use std::path::Path;
+
+/// One way of talking to a connected camera.
+///
+/// File paths are the paths returned by `list`: relative to the mount point
+/// for mass storage, absolute camera folders for PTP.
+pub trait CameraBackend: Send + Sync + fmt::Debug {
+    /// Connects to the camera plugged in as `device`, failing if this backend
+    /// cannot reach it.
+    fn detect(device: &UsbDevice) -> Result<Self, AppError>
+    where
+        Self: Sized;
+
+    /// Short name shown in logs and sent to the frontend.
+    fn name(&self) -> &'static str;
+
+    /// Local directory the files can be read from directly, if any.
+    fn mount_point(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Serial number and other details the camera reports. Best effort:
+    /// fields that cannot be read are left empty.
+    fn identity(&self) -> DeviceIdentity {
+        DeviceIdentity::default()
+    }
+
+    /// Lists every file on the camera with its metadata.
+    fn list(&self) -> Result<Vec<FileInfo>, AppError>;
+
+    /// Fresh metadata of one file. Sizes from `stat` are always exact.
+    fn stat(&self, file: &Path) -> Result<FileInfo, AppError>;
+
+    /// Reads up to `len` bytes of `file` starting at `offset`. Returns fewer
+    /// bytes only at the end of the file.
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Result<Vec<u8>, AppError>;
+
+    /// Whether `read` fetches only the bytes asked for. Files of a backend
+    /// that fetches the whole file on every read are always staged with
+    /// `download`, so they are fetched once instead of once per part.
+    fn supports_partial_reads(&self) -> bool {
+        true
+    }
+
+    /// Copies the whole of `file` to a new local file at `dest` in one
+    /// fetch. Returns the bytes copied. Only needed without partial reads.
+    fn download(&self, file: &Path, _dest: &Path) -> Result<u64, AppError> {
+        Err(AppError::CameraOperation(format!(
+            "{} cannot download {} whole",
+            self.name(),
+            file.display()
+        )))
+    }
+
+    fn delete(&self, file: &Path) -> Result<(), AppError>;
+}
+
+/// Splits a PTP path like `/store_00010001/DCIM/100RICOH/R0010001.JPG` into
+/// its folder and file name.
+pub fn split_camera_path(file: &Path) -> Result<(String, String), AppError> {
+    let folder = match file.parent().and_then(|p| p.to_str()) {
+        Some("") | None => "/",
+        Some(folder) => folder,
+    };
+    let name = file.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+        AppError::InvalidArgument(format!("Invalid camera file: {}", file.display()))
+    })?;
+
+    Ok((folder.to_string(), name.to_string()))
+}
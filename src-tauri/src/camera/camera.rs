@@ -1,274 +1,391 @@
-use crate::camera::device_type::{CameraInfo, CAMERAS};
-use serde::Serialize;
-use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use crate::cache::file_cache::rename_device_in_skipped_files;
+use crate::cache::job_cache::rename_device_in_upload_jobs;
+use crate::cache::upload_cache::rename_device_in_pending_uploads;
+use crate::camera::backend::CameraBackend;
+use crate::camera::device_type::{BackendKind, CameraInfo};
+use crate::camera::gphoto2_cli::Gphoto2CliBackend;
+use crate::camera::libgphoto2::LibGphoto2Backend;
+use crate::camera::mass_storage::MassStorageBackend;
+use crate::camera::pub_device_identity::DeviceIdentity;
+use crate::camera::pub_file_info::FileInfo;
+use crate::camera::simulated::{self, SimulatedBackend};
+use crate::camera::usb::{connected_cameras, UsbDevice};
+use crate::error::AppError;
+use once_cell::sync::Lazy;
+use serde::{Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+type DetectFn = fn(&UsbDevice) -> Result<Arc<dyn CameraBackend>, AppError>;
+
+/// Backends in the order they are tried, unless the camera prefers a kind.
+/// The first one that reaches the camera and finds files is used.
+const BACKENDS: [(&str, BackendKind, DetectFn); 3] = [
+    ("libgphoto2", BackendKind::Ptp, detect::<LibGphoto2Backend>),
+    ("gphoto2 CLI", BackendKind::Ptp, detect::<Gphoto2CliBackend>),
+    (
+        "mass storage",
+        BackendKind::MassStorage,
+        detect::<MassStorageBackend>,
+    ),
+];
+
+/// Only backend of simulated cameras, see `simulated::SIMULATED_CAMERA_VAR`.
+const SIMULATED_BACKEND: (&str, BackendKind, DetectFn) =
+    ("simulated", BackendKind::Ptp, detect::<SimulatedBackend>);
+
+/// Open connections to the cameras found by `find_cameras`, keyed by
+/// `UsbDevice::id`, so later operations on their files reach the same camera
+/// the same way.
+static CONNECTED: Lazy<Mutex<HashMap<String, Connection>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A camera's backend and what it reported about itself when connected.
+#[derive(Debug, Clone)]
+struct Connection {
+    backend: Arc<dyn CameraBackend>,
+    identity: DeviceIdentity,
+    device_id: String,
+}
 
 #[derive(Debug, Serialize)]
 pub struct CameraWithFiles {
+    /// Id used for this camera in uploads and the local caches, see
+    /// `device_id_for`
+    pub device_id: String,
+    /// Id of the USB connection, see `UsbDevice::id`. Camera events carry it
+    pub usb_id: String,
     pub info: &'static CameraInfo,
+    pub usb: UsbDevice,
+    pub identity: DeviceIdentity,
+    /// Backend used to reach the camera, if any could
+    #[serde(serialize_with = "serialize_backend")]
+    pub backend: Option<Arc<dyn CameraBackend>>,
     pub mount_point: Option<PathBuf>,
-    pub files: Vec<PathBuf>,
+    pub files: Vec<FileInfo>,
     pub access_error: Option<String>,
 }
 
-pub fn find_camera() -> Option<CameraWithFiles> {
-    // Attempt to enumerate USB devices
-    let devices = match rusb::devices() {
-        Ok(devices) => devices,
-        Err(e) => {
-            eprintln!("Failed to enumerate USB devices: {}", e);
-            return None;
-        }
-    };
-
-    // Iterate through all connected USB devices
-    for device in devices.iter() {
-        if let Ok(desc) = device.device_descriptor() {
-            let vendor_id = desc.vendor_id();
+/// Detects every supported camera plugged in and lists its files.
+pub fn find_cameras() -> Vec<CameraWithFiles> {
+    let cameras: Vec<CameraWithFiles> = connected_cameras()
+        .into_iter()
+        .map(|(usb, info)| connect(usb, info))
+        .collect();
 
-            // Check if this vendor ID matches any camera in our CAMERAS map
-            if let Some(camera_info) = CAMERAS.get(&vendor_id) {
-                println!("Found camera: {} (Vendor ID: {})", camera_info.device, vendor_id);
-
-                // Try to find the mounted storage device and list files
-                let (mount_point, files, access_error) = find_camera_files();
-
-                return Some(CameraWithFiles {
-                    info: camera_info,
-                    mount_point,
-                    files,
-                    access_error,
-                });
-            }
-        }
+    if cameras.is_empty() {
+        eprintln!("No supported camera found connected via USB");
     }
-
-    // No matching camera found
-    eprintln!("No supported camera found connected via USB");
-    None
+    cameras
 }
 
-fn find_camera_files_ptp() -> (Option<PathBuf>, Vec<PathBuf>, Option<String>) {
-    println!("Attempting PTP camera access via gphoto2 CLI...");
-
-    // First, check if gphoto2 is available
-    let check_gphoto2 = Command::new("which")
-        .arg("gphoto2")
-        .output();
-
-    if check_gphoto2.is_err() || !check_gphoto2.as_ref().unwrap().status.success() {
-        let error_msg = "gphoto2 CLI not found. Please install it with: brew install gphoto2".to_string();
-        eprintln!("{}", error_msg);
-        return (None, Vec::new(), Some(error_msg));
+/// Detects the camera with `device_id` and lists its files.
+pub fn find_camera(device_id: &str) -> Option<CameraWithFiles> {
+    let known_usb_id = connection_of(device_id).map(|(usb_id, _)| usb_id);
+    let mut cameras = connected_cameras();
+
+    let known = cameras.iter().position(|(usb, _)| {
+        let usb_id = usb.id();
+        usb_id == device_id || known_usb_id.as_ref() == Some(&usb_id)
+    });
+    if let Some(index) = known {
+        let (usb, info) = cameras.swap_remove(index);
+        return Some(connect(usb, info));
     }
 
-    // Try to detect cameras
-    let detect_output = Command::new("gphoto2")
-        .arg("--auto-detect")
-        .output();
+    // Ids from a PTP serial number or a volume are only known once connected
+    cameras
+        .into_iter()
+        .map(|(usb, info)| connect(usb, info))
+        .find(|camera| camera.device_id == device_id)
+}
 
-    if let Err(e) = detect_output {
-        let error_msg = format!("Failed to run gphoto2 --auto-detect: {}", e);
-        eprintln!("{}", error_msg);
-        return (None, Vec::new(), Some(error_msg));
+/// The camera an operation targets: the one with `device_id`, or the only
+/// one plugged in when no id is given.
+pub fn select_camera(device_id: Option<&str>) -> Result<CameraWithFiles, AppError> {
+    if let Some(device_id) = device_id {
+        return find_camera(device_id).ok_or(AppError::CameraNotFound);
     }
 
-    let detect_output = detect_output.unwrap();
-    let detect_stdout = String::from_utf8_lossy(&detect_output.stdout);
+    let mut cameras = connected_cameras();
+    match cameras.len() {
+        0 => Err(AppError::CameraNotFound),
+        1 => {
+            let (usb, info) = cameras.remove(0);
+            Ok(connect(usb, info))
+        }
+        count => Err(AppError::InvalidArgument(format!(
+            "{} cameras are connected; choose one",
+            count
+        ))),
+    }
+}
 
-    // Check if any camera was detected
-    if !detect_stdout.contains("usb:") {
-        let error_msg = "No PTP camera detected by gphoto2".to_string();
-        eprintln!("{}", error_msg);
-        return (None, Vec::new(), Some(error_msg));
+/// Returns the backend of a connected camera, detecting it again if it has
+/// not been seen since the app started.
+pub fn backend_for(device_id: &str) -> Result<Arc<dyn CameraBackend>, AppError> {
+    if let Some((_, connection)) = connection_of(device_id) {
+        return Ok(connection.backend);
     }
+    find_camera(device_id)
+        .and_then(|camera| camera.backend)
+        .ok_or(AppError::CameraNotFound)
+}
 
-    println!("Camera detected via gphoto2: {}", detect_stdout.trim());
+/// Drops the connection to a camera that was unplugged. Uploads still
+/// holding its backend fail on their next read.
+pub fn forget(usb_id: &str) {
+    CONNECTED.lock().unwrap().remove(usb_id);
+}
 
-    // List files on the camera
-    let list_output = Command::new("gphoto2")
-        .arg("--list-files")
-        .output();
+/// Whether `mount_point` is the card of a connected camera other than the
+/// one at `usb_id`.
+pub fn is_claimed(mount_point: &Path, usb_id: &str) -> bool {
+    CONNECTED.lock().unwrap().iter().any(|(id, connection)| {
+        id != usb_id && connection.backend.mount_point() == Some(mount_point)
+    })
+}
 
-    if let Err(e) = list_output {
-        let error_msg = format!("Failed to run gphoto2 --list-files: {}", e);
-        eprintln!("{}", error_msg);
-        return (None, Vec::new(), Some(error_msg));
+/// Id used for a camera in uploads: its serial number, read over USB or
+/// else over PTP; else a hash of its card's volume UUID; else the USB port it
+/// is plugged into. Prefixed with the USB vendor and product id.
+fn device_id_for(usb: &UsbDevice, identity: &DeviceIdentity) -> String {
+    let ids = format!("{:04x}:{:04x}", usb.vendor_id, usb.product_id);
+    match (&usb.serial, &identity.serial, &identity.volume_id) {
+        (Some(serial), _, _) | (None, Some(serial), _) => format!("{}:{}", ids, serial),
+        (None, None, Some(volume_id)) => format!("{}:vol-{}", ids, volume_id),
+        (None, None, None) => usb.id(),
     }
+}
 
-    let list_output = list_output.unwrap();
-    let list_stdout = String::from_utf8_lossy(&list_output.stdout);
-
-    // Parse file list from gphoto2 output
-    let files = parse_gphoto2_file_list(&list_stdout);
+/// Lists the camera's files, reusing its connection from an earlier
+/// detection if it still works, and otherwise trying each backend in
+/// priority order until one lists files.
+fn connect(usb: UsbDevice, info: &'static CameraInfo) -> CameraWithFiles {
+    let usb_id = usb.id();
+    println!(
+        "Found camera: {} (Vendor ID: {}, USB id {})",
+        info.device, info.vendor_id, usb_id
+    );
+
+    // A second connection could not claim a camera the first one still holds
+    if let Some(connection) = connection(&usb_id) {
+        match connection.backend.list() {
+            Ok(files) => {
+                let files = captures(info, files);
+                return with_files(usb, info, connection, files);
+            }
+            Err(e) => {
+                eprintln!("Lost connection via {}: {}", connection.backend.name(), e);
+                forget(&usb_id);
+            }
+        }
+    }
 
-    if files.is_empty() {
-        let error_msg = "Camera connected via PTP but no files found".to_string();
-        eprintln!("{}", error_msg);
-        (Some(PathBuf::from("PTP")), Vec::new(), Some(error_msg))
+    // Stable sort, so backends of the preferred kind keep their order
+    let mut backends = if simulated::is_enabled() {
+        vec![SIMULATED_BACKEND]
     } else {
-        println!("Found {} files via PTP", files.len());
-        (Some(PathBuf::from("PTP")), files, None)
+        BACKENDS.to_vec()
+    };
+    backends.sort_by_key(|(_, kind, _)| Some(*kind) != info.preferred_backend);
+
+    let mut access_error = None;
+    for (name, _, detect) in backends {
+        let backend = match detect(&usb) {
+            Ok(backend) => backend,
+            Err(e) => {
+                eprintln!("{} unavailable: {}", name, e);
+                access_error = Some(describe(e));
+                continue;
+            }
+        };
+
+        match backend.list().map(|files| captures(info, files)) {
+            Ok(files) if !files.is_empty() => {
+                println!("Found {} files via {}", files.len(), name);
+                let connection = register(&usb, backend);
+                adopt_legacy_records(info, &connection.device_id, &files);
+                return with_files(usb, info, connection, files);
+            }
+            Ok(_) => {
+                let error_msg = format!("Camera connected via {} but no files found", name);
+                eprintln!("{}", error_msg);
+                access_error = Some(error_msg);
+            }
+            Err(e) => {
+                eprintln!("Error listing files via {}: {}", name, e);
+                access_error = Some(describe(e));
+            }
+        }
+    }
+
+    let identity = identity_of(&usb, DeviceIdentity::default());
+    CameraWithFiles {
+        device_id: device_id_for(&usb, &identity),
+        usb_id,
+        info,
+        usb,
+        identity,
+        backend: None,
+        mount_point: None,
+        files: Vec::new(),
+        access_error,
     }
 }
 
-fn parse_gphoto2_file_list(output: &str) -> Vec<PathBuf> {
-    let mut files = Vec::new();
-    let mut current_folder = String::new();
-
-    for line in output.lines() {
-        // Look for folder lines like "There are N files in folder '/path'."
-        if line.starts_with("There") && line.contains("files in folder") {
-            if let Some(start) = line.find('\'') {
-                if let Some(end) = line[start + 1..].find('\'') {
-                    current_folder = line[start + 1..start + 1 + end].to_string();
-                }
-            }
+/// Fills in what the backend could not read from the USB descriptors.
+fn identity_of(usb: &UsbDevice, mut identity: DeviceIdentity) -> DeviceIdentity {
+    identity.serial = identity.serial.or_else(|| usb.serial.clone());
+    identity.model = identity.model.or_else(|| usb.product.clone());
+    identity
+}
+
+/// Keeps the files the camera's layout marks as captures, and links the
+/// files of dual-file captures through their `capture` key.
+fn captures(info: &CameraInfo, files: Vec<FileInfo>) -> Vec<FileInfo> {
+    let mut captures: Vec<FileInfo> = files
+        .into_iter()
+        .filter(|file| info.layout.is_capture(&file.path))
+        .collect();
+
+    let mut halves: HashMap<String, usize> = HashMap::new();
+    for file in &mut captures {
+        file.capture = info
+            .layout
+            .capture_key(&file.filename)
+            .map(|key| format!("{}/{}", file.folder, key));
+        if let Some(capture) = &file.capture {
+            *halves.entry(capture.clone()).or_default() += 1;
         }
-        // Look for file lines starting with #N
-        else if line.starts_with('#') {
-            // Parse file line format: #1     R0010001.JPG               rd  8367 KB ...
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let filename = parts[1];
-                let file_path = if current_folder.is_empty() {
-                    PathBuf::from(filename)
-                } else {
-                    PathBuf::from(format!("{}/{}", current_folder, filename))
-                };
-                files.push(file_path);
-            }
+    }
+    for (capture, count) in halves {
+        if count < info.layout.dual_file_markers.len() {
+            eprintln!("Warning: capture {} is missing some of its files", capture);
         }
     }
 
-    files
+    captures
 }
 
-fn find_camera_files() -> (Option<PathBuf>, Vec<PathBuf>, Option<String>) {
-    // First, try PTP access via gphoto2
-    let (mount_point, files, error) = find_camera_files_ptp();
-    if mount_point.is_some() && !files.is_empty() {
-        return (mount_point, files, error);
+fn with_files(
+    usb: UsbDevice,
+    info: &'static CameraInfo,
+    connection: Connection,
+    files: Vec<FileInfo>,
+) -> CameraWithFiles {
+    CameraWithFiles {
+        device_id: connection.device_id,
+        usb_id: usb.id(),
+        info,
+        usb,
+        identity: connection.identity,
+        mount_point: connection.backend.mount_point().map(PathBuf::from),
+        backend: Some(connection.backend),
+        files,
+        access_error: None,
     }
+}
 
-    // If PTP didn't work, fall back to mass storage detection
-    println!("PTP access failed or no files found, trying mass storage detection...");
+fn detect<B: CameraBackend + 'static>(
+    device: &UsbDevice,
+) -> Result<Arc<dyn CameraBackend>, AppError> {
+    Ok(Arc::new(B::detect(device)?))
+}
 
-    // Try to enumerate mounted drives
-    let drives = match bb_drivelist::drive_list() {
-        Ok(drives) => drives,
-        Err(e) => {
-            let error_msg = format!("Failed to enumerate drives: {}", e);
-            eprintln!("{}", error_msg);
-            return (None, Vec::new(), Some(error_msg));
-        }
+/// Reads the identity of a newly connected camera and keeps its connection.
+fn register(usb: &UsbDevice, backend: Arc<dyn CameraBackend>) -> Connection {
+    let identity = backend.identity();
+    let device_id = device_id_for(usb, &identity);
+    let connection = Connection {
+        backend,
+        identity: identity_of(usb, identity),
+        device_id,
     };
+    println!("Camera {} has device id {}", usb.id(), connection.device_id);
 
-    println!("Total drives detected: {}", drives.len());
-
-    // Look for removable drives (cameras typically mount as removable storage)
-    for drive in &drives {
-        println!("Drive: device={}, is_removable={}, mountpoints={}",
-                 drive.device, drive.is_removable, drive.mountpoints.len());
+    CONNECTED
+        .lock()
+        .unwrap()
+        .insert(usb.id(), connection.clone());
+    connection
+}
 
-        for mp in &drive.mountpoints {
-            println!("  Mountpoint: {}", mp.path);
-        }
+/// Moves what the caches hold under the id cameras had before they were
+/// told apart by serial number, the model name, over to `device_id`.
+///
+/// Only records of the files in the camera's listing `files`, matched by
+/// filename and size, move, and only while it is the one camera of its model
+/// plugged in. Anything else could belong to another camera of the model, so
+/// it stays under the legacy id.
+fn adopt_legacy_records(info: &CameraInfo, device_id: &str, files: &[FileInfo]) {
+    let legacy_id = info.device.as_str();
+    if legacy_id == device_id {
+        return;
     }
-
-    // First, try removable drives
-    for drive in &drives {
-        if !drive.is_removable {
-            continue;
-        }
-
-        // Try each mount point
-        for mount_point in &drive.mountpoints {
-            let path = PathBuf::from(&mount_point.path);
-            println!("Checking removable drive at: {}", path.display());
-
-            match list_files_recursive(&path, &path) {
-                Ok(files) if !files.is_empty() => {
-                    println!("Found {} files on device at {}", files.len(), path.display());
-                    return (Some(path.clone()), files, None);
-                }
-                Ok(_) => {
-                    println!("No files found at {}", path.display());
-                }
-                Err(e) => {
-                    let error_msg = format!(
-                        "Cannot access files at {}. Error: {}. \
-                        On macOS, you may need to grant 'Full Disk Access' permission to your terminal or app in System Settings > Privacy & Security > Full Disk Access.",
-                        path.display(),
-                        e
-                    );
-                    eprintln!("{}", error_msg);
-                    return (Some(path.clone()), Vec::new(), Some(error_msg));
-                }
-            }
-        }
+    let same_model = connected_cameras()
+        .iter()
+        .filter(|(_, other)| other.device == info.device)
+        .count();
+    if same_model != 1 {
+        return;
     }
 
-    // On macOS, RICOH THETA cameras might mount at /Volumes/
-    // Try common RICOH THETA mount points
-    let potential_paths = vec![
-        "/Volumes/RICOH THETA",
-        "/Volumes/RICOH THETA Z1",
-        "/Volumes/NO NAME",
-    ];
-
-    for path_str in potential_paths {
-        let path = PathBuf::from(path_str);
-        if path.exists() {
-            println!("Found potential RICOH mount at: {}", path.display());
-            match list_files_recursive(&path, &path) {
-                Ok(files) if !files.is_empty() => {
-                    println!("Found {} files on device at {}", files.len(), path.display());
-                    return (Some(path.clone()), files, None);
-                }
-                Ok(_) => {
-                    println!("No files found at {}", path.display());
-                }
-                Err(e) => {
-                    eprintln!("Error accessing {}: {}", path.display(), e);
-                }
-            }
-        }
-    }
+    let listed: HashSet<(&str, i64)> = files
+        .iter()
+        .map(|file| (file.filename.as_str(), file.size as i64))
+        .collect();
+    let is_on_device = |filename: &str, size: i64| listed.contains(&(filename, size));
 
-    let error_msg = "Camera found but no mounted storage device detected. Please ensure the camera is in the correct USB mode (usually 'Mass Storage' or 'File Transfer' mode).".to_string();
-    eprintln!("{}", error_msg);
-    (None, Vec::new(), Some(error_msg))
+    let report = |records: &str, moved: Result<usize, AppError>| match moved {
+        Ok(0) => {}
+        Ok(count) => println!("Moved {} {} of {} to {}", count, records, legacy_id, device_id),
+        Err(e) => eprintln!("Failed to move {} of {}: {}", records, legacy_id, e),
+    };
+    report(
+        "uploaded files",
+        rename_device_in_skipped_files(legacy_id, device_id, is_on_device),
+    );
+    report(
+        "pending uploads",
+        rename_device_in_pending_uploads(legacy_id, device_id, is_on_device),
+    );
+    report(
+        "upload jobs",
+        rename_device_in_upload_jobs(legacy_id, device_id, is_on_device),
+    );
 }
 
-fn list_files_recursive(base_path: &PathBuf, current_path: &PathBuf) -> std::io::Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-
-    let entries = fs::read_dir(current_path)?;
+fn connection(usb_id: &str) -> Option<Connection> {
+    CONNECTED.lock().unwrap().get(usb_id).cloned()
+}
 
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+/// The connection with `device_id`, and its USB id.
+fn connection_of(device_id: &str) -> Option<(String, Connection)> {
+    CONNECTED
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(_, connection)| connection.device_id == device_id)
+        .map(|(usb_id, connection)| (usb_id.clone(), connection.clone()))
+}
 
-        if path.is_dir() {
-            // Recursively list files in subdirectories
-            match list_files_recursive(base_path, &path) {
-                Ok(mut subfiles) => files.append(&mut subfiles),
-                Err(e) => {
-                    eprintln!("Warning: Could not read directory {}: {}", path.display(), e);
-                    // Continue with other directories
-                }
-            }
-        } else if path.is_file() {
-            // Store relative path from base mount point
-            if let Ok(relative_path) = path.strip_prefix(base_path) {
-                files.push(relative_path.to_path_buf());
-            } else {
-                files.push(path);
-            }
-        }
+/// Message for `access_error`. Camera errors are unwrapped because
+/// `get_camera_files` wraps `access_error` in `CameraOperation` again.
+fn describe(error: AppError) -> String {
+    match error {
+        AppError::CameraOperation(message) => message,
+        other => other.to_string(),
     }
+}
 
-    Ok(files)
-}
\ No newline at end of file
+fn serialize_backend<S: Serializer>(
+    backend: &Option<Arc<dyn CameraBackend>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    backend
+        .as_ref()
+        .map(|backend| backend.name())
+        .serialize(serializer)
+}
@@ -1,12 +1,23 @@
+use crate::cache::root_cache::STORAGE_PATH;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Name of the file in the cache directory whose entries override or extend
+/// the built-in camera registry.
+pub const CAMERA_REGISTRY_FILE: &str = "cameras.json";
+
+/// Cameras known at build time.
+const EMBEDDED_REGISTRY: &str = include_str!("cameras.json");
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum VendorType {
     Insta,
     Theta,
+    GoPro,
 }
 
 impl fmt::Display for VendorType {
@@ -14,47 +25,258 @@ impl fmt::Display for VendorType {
         match self {
             VendorType::Insta => write!(f, "Insta"),
             VendorType::Theta => write!(f, "Theta"),
+            VendorType::GoPro => write!(f, "GoPro"),
         }
     }
 }
+
+/// How a backend reaches the camera's files.
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
-pub enum DeviceType {
-    Insta360OneX2,
-    ThetaZ1,
+#[serde(rename_all = "camelCase")]
+pub enum BackendKind {
+    /// PTP/MTP, through libgphoto2 or the gphoto2 CLI
+    Ptp,
+    /// The card mounted as a removable drive
+    MassStorage,
 }
-impl fmt::Display for DeviceType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DeviceType::Insta360OneX2 => write!(f, "Insta360 One X2"),
-            DeviceType::ThetaZ1 => write!(f, "RICOH THETA Z1"),
+
+/// Where a model keeps its captures on the card. Empty lists accept anything.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StorageLayout {
+    /// Folders holding captures, such as `100RICOH`. A single `*` matches
+    /// any run of characters, as in `*RICOH` or `Camera*`
+    pub folders: Vec<String>,
+    /// Extensions of capture files, lowercase and without the dot. Anything
+    /// else on the card, such as thumbnails and proxies, is not uploaded
+    pub extensions: Vec<String>,
+    /// Markers that tell apart the files of a capture saved as one file per
+    /// lens, such as `_00_` and `_10_` in
+    /// `VID_20240101_120000_00_001.insv` and `VID_20240101_120000_10_001.insv`
+    pub dual_file_markers: Vec<String>,
+}
+
+impl StorageLayout {
+    /// Whether the file at `path` on the card is a capture.
+    pub fn is_capture(&self, path: &Path) -> bool {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let has_extension = self.extensions.is_empty()
+            || extension.is_some_and(|extension| self.extensions.contains(&extension));
+
+        let in_folder = self.folders.is_empty()
+            || path.parent().is_some_and(|folder| {
+                folder.components().any(|component| {
+                    let name = component.as_os_str().to_string_lossy();
+                    self.folders
+                        .iter()
+                        .any(|pattern| matches_pattern(pattern, &name))
+                })
+            });
+
+        has_extension && in_folder
+    }
+
+    /// Key shared by the files of one dual-file capture: the file name with
+    /// its lens marker removed. `None` for single-file captures.
+    pub fn capture_key(&self, filename: &str) -> Option<String> {
+        self.dual_file_markers
+            .iter()
+            .find(|marker| filename.contains(marker.as_str()))
+            .map(|marker| filename.replacen(marker.as_str(), "_", 1))
+    }
+}
+
+/// Matches `name` against `pattern`, in which a single `*` matches any run of
+/// characters. Case-insensitive, as card file systems are.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.to_ascii_lowercase(), name.to_ascii_lowercase());
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            name.len() >= prefix.len() + suffix.len()
+                && name.starts_with(prefix)
+                && name.ends_with(suffix)
         }
+        None => name == pattern,
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct CameraInfo {
     pub vendor: VendorType,
     pub vendor_id: u16,
-    pub device: DeviceType,
-}
-
-pub static CAMERAS: Lazy<HashMap<u16, CameraInfo>> = Lazy::new(|| {
-    HashMap::from([
-        (
-            1802,
-            CameraInfo {
-                vendor: VendorType::Insta,
-                vendor_id: 1802,
-                device: DeviceType::Insta360OneX2,
-            },
-        ),
-        (
-            1482,
-            CameraInfo {
-                vendor: VendorType::Theta,
-                vendor_id: 1482,
-                device: DeviceType::ThetaZ1,
-            },
-        ),
-    ])
+    /// Matched first when present
+    #[serde(default)]
+    pub product_id: Option<u16>,
+    /// Product name the camera reports over USB, matched only for a product
+    /// id that no entry has
+    #[serde(default)]
+    pub product_name: Option<String>,
+    /// Model name shown to the user
+    pub device: String,
+    #[serde(default)]
+    pub layout: StorageLayout,
+    /// Kind of backend tried first, for models that work better one way
+    #[serde(default)]
+    pub preferred_backend: Option<BackendKind>,
+}
+
+/// Supported cameras, keyed by USB vendor and product id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CameraRegistry {
+    cameras: Vec<CameraInfo>,
+}
+
+impl CameraRegistry {
+    /// The camera with exactly these ids. A product id no entry has, such as
+    /// one a model reports in another USB mode, is matched by product name
+    /// instead. Any other device of a supported vendor is not a camera.
+    pub fn get(
+        &self,
+        vendor_id: u16,
+        product_id: u16,
+        product_name: Option<&str>,
+    ) -> Option<&CameraInfo> {
+        let of_vendor = || {
+            self.cameras
+                .iter()
+                .filter(move |camera| camera.vendor_id == vendor_id)
+        };
+
+        if let Some(camera) = of_vendor().find(|camera| camera.product_id == Some(product_id)) {
+            return Some(camera);
+        }
+        let product_name = product_name?;
+        of_vendor().find(|camera| {
+            camera
+                .product_name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(product_name))
+        })
+    }
+
+    /// Whether any supported camera has this vendor id.
+    pub fn has_vendor(&self, vendor_id: u16) -> bool {
+        self.cameras
+            .iter()
+            .any(|camera| camera.vendor_id == vendor_id)
+    }
+
+    /// Adds `other`'s cameras, replacing entries matched the same way.
+    fn merge(&mut self, other: CameraRegistry) {
+        for camera in other.cameras {
+            self.cameras.retain(|existing| {
+                existing.vendor_id != camera.vendor_id
+                    || existing.product_id != camera.product_id
+                    || existing.product_name != camera.product_name
+            });
+            self.cameras.push(camera);
+        }
+    }
+}
+
+/// The embedded registry merged with the override file, read once per run.
+pub static CAMERAS: Lazy<CameraRegistry> = Lazy::new(|| {
+    let mut registry: CameraRegistry =
+        serde_json::from_str(EMBEDDED_REGISTRY).expect("Invalid embedded camera registry");
+
+    let path = STORAGE_PATH.join(CAMERA_REGISTRY_FILE);
+    match fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str::<CameraRegistry>(&content) {
+            Ok(overrides) => {
+                println!(
+                    "Loaded {} camera definitions from {}",
+                    overrides.cameras.len(),
+                    path.display()
+                );
+                registry.merge(overrides);
+            }
+            Err(e) => eprintln!("Ignoring invalid camera registry {}: {}", path.display(), e),
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => eprintln!("Failed to read camera registry {}: {}", path.display(), e),
+    }
+
+    registry
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedded() -> CameraRegistry {
+        serde_json::from_str(EMBEDDED_REGISTRY).unwrap()
+    }
+
+    #[test]
+    fn every_embedded_camera_has_a_product_id() {
+        for camera in embedded().cameras {
+            assert!(
+                camera.product_id.is_some(),
+                "{} has no productId",
+                camera.device
+            );
+        }
+    }
+
+    #[test]
+    fn embedded_product_ids_are_unique() {
+        let cameras = embedded().cameras;
+        for (i, camera) in cameras.iter().enumerate() {
+            assert!(
+                !cameras[i + 1..]
+                    .iter()
+                    .any(|other| other.vendor_id == camera.vendor_id
+                        && other.product_id == camera.product_id),
+                "{} shares its ids with another camera",
+                camera.device
+            );
+        }
+    }
+
+    #[test]
+    fn pattern_with_wildcard_matches_prefix_and_suffix() {
+        assert!(matches_pattern("*RICOH", "100RICOH"));
+        assert!(matches_pattern("*ricoh", "100RICOH"));
+        assert!(matches_pattern("DCIM*", "DCIM"));
+        assert!(matches_pattern("1*MEDIA", "100MEDIA"));
+        assert!(!matches_pattern("*RICOH", "100GOPRO"));
+        // The prefix and suffix must not overlap
+        assert!(!matches_pattern("AB*BC", "ABC"));
+    }
+
+    #[test]
+    fn pattern_without_wildcard_matches_whole_name() {
+        assert!(matches_pattern("DCIM", "dcim"));
+        assert!(!matches_pattern("DCIM", "DCIM1"));
+    }
+
+    #[test]
+    fn get_matches_product_id_before_name() {
+        let registry = embedded();
+
+        let camera = registry.get(1482, 878, Some("RICOH THETA Z1")).unwrap();
+        assert_eq!(camera.device, "RICOH THETA X");
+    }
+
+    #[test]
+    fn get_falls_back_to_product_name() {
+        let registry = embedded();
+
+        let camera = registry.get(1482, 1, Some("ricoh theta z1")).unwrap();
+        assert_eq!(camera.device, "RICOH THETA Z1");
+    }
+
+    #[test]
+    fn get_ignores_other_devices_of_a_vendor() {
+        let registry = embedded();
+
+        assert!(registry.has_vendor(1482));
+        assert!(registry.get(1482, 1, None).is_none());
+        assert!(registry.get(1482, 1, Some("RICOH Printer")).is_none());
+        assert!(registry.get(1, 877, Some("RICOH THETA Z1")).is_none());
+    }
+}
@@ -0,0 +1,469 @@
+//! Safe owned wrappers around the raw `gphoto2-sys` calls.
+//!
+//! Every type releases its libgphoto2 object in `Drop`, so early returns on
+//! errors can neither leak nor double-free. Failing calls are mapped onto
+//! `AppError::CameraOperation` with libgphoto2's own error message.
+
+use crate::error::AppError;
+use libc::{c_char, c_int};
+use std::ffi::{CStr, CString};
+use std::fmt;
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::ptr::{self, NonNull};
+
+/// Fails with a `CameraOperation` error unless `ret` is `GP_OK`.
+fn check(ret: c_int, action: &str) -> Result<(), AppError> {
+    if ret == gphoto2_sys::GP_OK {
+        Ok(())
+    } else {
+        Err(AppError::CameraOperation(format!(
+            "{}: {}",
+            action,
+            error_string(ret)
+        )))
+    }
+}
+
+fn error_string(error_code: c_int) -> String {
+    // SAFETY: returns a pointer to a static string, or null
+    let error_ptr = unsafe { gphoto2_sys::gp_result_as_string(error_code) };
+    if error_ptr.is_null() {
+        return format!("Unknown error ({})", error_code);
+    }
+
+    // SAFETY: non-null and NUL-terminated
+    match unsafe { CStr::from_ptr(error_ptr) }.to_str() {
+        Ok(s) => s.to_string(),
+        Err(_) => format!("Error code: {}", error_code),
+    }
+}
+
+fn c_string(value: &str) -> Result<CString, AppError> {
+    CString::new(value)
+        .map_err(|e| AppError::InvalidArgument(format!("Invalid camera path {:?}: {}", value, e)))
+}
+
+/// A libgphoto2 `GPContext`, which carries progress and cancellation for
+/// the calls made with it.
+pub struct Context(NonNull<gphoto2_sys::GPContext>);
+
+impl Context {
+    pub fn new() -> Result<Self, AppError> {
+        // SAFETY: no preconditions; null signals failure
+        let context = unsafe { gphoto2_sys::gp_context_new() };
+        NonNull::new(context).map(Self).ok_or_else(|| {
+            AppError::CameraOperation("Failed to create gphoto2 context".to_string())
+        })
+    }
+
+    fn as_ptr(&self) -> *mut gphoto2_sys::GPContext {
+        self.0.as_ptr()
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        // SAFETY: we own the only reference
+        unsafe { gphoto2_sys::gp_context_unref(self.as_ptr()) }
+    }
+}
+
+/// A libgphoto2 `CameraList` of name/value pairs.
+pub struct CameraList(NonNull<gphoto2_sys::CameraList>);
+
+impl CameraList {
+    pub fn new() -> Result<Self, AppError> {
+        let mut list = ptr::null_mut();
+        // SAFETY: `list` is a valid out pointer
+        check(
+            unsafe { gphoto2_sys::gp_list_new(&mut list) },
+            "Failed to create list",
+        )?;
+        NonNull::new(list)
+            .map(Self)
+            .ok_or_else(|| AppError::CameraOperation("Failed to create list".to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        // SAFETY: the list is valid for as long as `self`
+        let count = unsafe { gphoto2_sys::gp_list_count(self.as_ptr()) };
+        count.max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Name of the entry at `index`, if it exists and is valid UTF-8.
+    pub fn name(&self, index: usize) -> Option<String> {
+        let mut name_ptr: *const c_char = ptr::null();
+        // SAFETY: the list is valid and `name_ptr` is a valid out pointer
+        let ret =
+            unsafe { gphoto2_sys::gp_list_get_name(self.as_ptr(), index as c_int, &mut name_ptr) };
+        if ret != gphoto2_sys::GP_OK || name_ptr.is_null() {
+            return None;
+        }
+        // SAFETY: the name is NUL-terminated and owned by the list, which
+        // outlives this borrow; it is copied out before returning
+        unsafe { CStr::from_ptr(name_ptr) }
+            .to_str()
+            .ok()
+            .map(str::to_string)
+    }
+
+    /// Names of every entry, skipping any that cannot be read.
+    pub fn names(&self) -> Vec<String> {
+        (0..self.len()).filter_map(|i| self.name(i)).collect()
+    }
+
+    fn as_ptr(&self) -> *mut gphoto2_sys::CameraList {
+        self.0.as_ptr()
+    }
+}
+
+impl Drop for CameraList {
+    fn drop(&mut self) {
+        // SAFETY: we own the only reference
+        unsafe { gphoto2_sys::gp_list_unref(self.as_ptr()) };
+    }
+}
+
+/// libgphoto2's list of the ports it can reach cameras on.
+pub struct PortInfoList(NonNull<gphoto2_sys::GPPortInfoList>);
+
+impl PortInfoList {
+    pub fn load() -> Result<Self, AppError> {
+        let mut list = ptr::null();
+        // SAFETY: `list` is a valid out pointer
+        check(
+            unsafe { gphoto2_sys::gp_port_info_list_new(&mut list) },
+            "Failed to create port list",
+        )?;
+        let list = NonNull::new(list as *mut gphoto2_sys::GPPortInfoList)
+            .map(Self)
+            .ok_or_else(|| AppError::CameraOperation("Failed to create port list".to_string()))?;
+
+        // SAFETY: the list is valid
+        check(
+            unsafe { gphoto2_sys::gp_port_info_list_load(list.as_ptr()) },
+            "Failed to load port list",
+        )?;
+        Ok(list)
+    }
+
+    /// Info of the port at `path`, such as `usb:001,007`. Borrowed from the
+    /// list, so it must not outlive it.
+    fn lookup(&self, path: &str) -> Result<gphoto2_sys::GPPortInfo, AppError> {
+        let path_cstr = c_string(path)?;
+        // SAFETY: both pointers are valid for the duration of the call
+        let index =
+            unsafe { gphoto2_sys::gp_port_info_list_lookup_path(self.as_ptr(), path_cstr.as_ptr()) };
+        if index < 0 {
+            return Err(AppError::CameraOperation(format!(
+                "Port {} not found: {}",
+                path,
+                error_string(index)
+            )));
+        }
+
+        let mut info = ptr::null_mut();
+        // SAFETY: `index` is in range and `info` is a valid out pointer
+        check(
+            unsafe { gphoto2_sys::gp_port_info_list_get_info(self.as_ptr(), index, &mut info) },
+            &format!("Failed to read port {}", path),
+        )?;
+        Ok(info)
+    }
+
+    fn as_ptr(&self) -> *mut gphoto2_sys::GPPortInfoList {
+        self.0.as_ptr()
+    }
+}
+
+impl Drop for PortInfoList {
+    fn drop(&mut self) {
+        // SAFETY: we own the list
+        unsafe { gphoto2_sys::gp_port_info_list_free(self.as_ptr()) };
+    }
+}
+
+/// A libgphoto2 `CameraFile` that writes what is downloaded into it to a
+/// local file, so large videos are never held in memory.
+pub struct CameraFile(NonNull<gphoto2_sys::CameraFile>);
+
+impl CameraFile {
+    /// Writes to `dest` from its current position.
+    #[cfg(unix)]
+    pub fn to_file(dest: &File) -> Result<Self, AppError> {
+        // libgphoto2 takes ownership of the descriptor and closes it on free
+        let fd = dest.try_clone()?.into_raw_fd();
+        let mut file = ptr::null_mut();
+        // SAFETY: `file` is a valid out pointer and `fd` an open descriptor
+        let ret = unsafe { gphoto2_sys::gp_file_new_from_fd(&mut file, fd) };
+        if let Err(e) = check(ret, "Failed to create file") {
+            // SAFETY: not taken by libgphoto2, so still ours to close
+            drop(unsafe { File::from_raw_fd(fd) });
+            return Err(e);
+        }
+        NonNull::new(file)
+            .map(Self)
+            .ok_or_else(|| AppError::CameraOperation("Failed to create file".to_string()))
+    }
+
+    fn as_ptr(&self) -> *mut gphoto2_sys::CameraFile {
+        self.0.as_ptr()
+    }
+}
+
+impl Drop for CameraFile {
+    fn drop(&mut self) {
+        // SAFETY: we own the only reference
+        unsafe { gphoto2_sys::gp_file_unref(self.as_ptr()) };
+    }
+}
+
+/// What the camera reports about a file. Fields the camera leaves out are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CameraFileInfo {
+    pub size: Option<u64>,
+    pub mime_type: Option<String>,
+    /// Seconds since the Unix epoch
+    pub mtime: Option<i64>,
+}
+
+/// A connected libgphoto2 `Camera`, with the context its calls run in.
+///
+/// Dropping it closes the connection and releases the camera for other apps.
+pub struct Camera {
+    camera: NonNull<gphoto2_sys::Camera>,
+    context: Context,
+}
+
+// SAFETY: libgphoto2 objects are not tied to the thread that created them.
+// `Camera` is not `Sync`, so sharing one needs a lock, which serializes calls.
+unsafe impl Send for Camera {}
+
+impl fmt::Debug for Camera {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Camera").finish_non_exhaustive()
+    }
+}
+
+impl Camera {
+    /// Connects to the camera on `port`, such as `usb:001,007`.
+    pub fn open(port: &str) -> Result<Self, AppError> {
+        let context = Context::new()?;
+        let ports = PortInfoList::load()?;
+        let port_info = ports.lookup(port)?;
+
+        let mut camera = ptr::null_mut();
+        // SAFETY: `camera` is a valid out pointer
+        check(
+            unsafe { gphoto2_sys::gp_camera_new(&mut camera) },
+            "Failed to create camera object",
+        )?;
+        let camera = NonNull::new(camera).ok_or_else(|| {
+            AppError::CameraOperation("Failed to create camera object".to_string())
+        })?;
+
+        // With the port set, init only picks a camera detected on that port.
+        // The port info is copied, so `ports` may be dropped afterwards.
+        // SAFETY: both objects are valid
+        let ret = unsafe {
+            match gphoto2_sys::gp_camera_set_port_info(camera.as_ptr(), port_info) {
+                gphoto2_sys::GP_OK => gphoto2_sys::gp_camera_init(camera.as_ptr(), context.as_ptr()),
+                error => error,
+            }
+        };
+        if let Err(e) = check(ret, &format!("Failed to initialize camera on {}", port)) {
+            // Not initialized, so only the reference is released
+            // SAFETY: we own the only reference
+            unsafe { gphoto2_sys::gp_camera_unref(camera.as_ptr()) };
+            return Err(e);
+        }
+
+        Ok(Self { camera, context })
+    }
+
+    /// Names of the files directly in `folder`.
+    pub fn list_files(&self, folder: &str) -> Result<Vec<String>, AppError> {
+        let folder_cstr = c_string(folder)?;
+        let list = CameraList::new()?;
+        // SAFETY: all pointers are valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_folder_list_files(
+                    self.as_ptr(),
+                    folder_cstr.as_ptr(),
+                    list.as_ptr(),
+                    self.context.as_ptr(),
+                )
+            },
+            &format!("Failed to list files in {}", folder),
+        )?;
+        Ok(list.names())
+    }
+
+    /// Names of the folders directly in `folder`.
+    pub fn list_folders(&self, folder: &str) -> Result<Vec<String>, AppError> {
+        let folder_cstr = c_string(folder)?;
+        let list = CameraList::new()?;
+        // SAFETY: all pointers are valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_folder_list_folders(
+                    self.as_ptr(),
+                    folder_cstr.as_ptr(),
+                    list.as_ptr(),
+                    self.context.as_ptr(),
+                )
+            },
+            &format!("Failed to list folders in {}", folder),
+        )?;
+        Ok(list.names())
+    }
+
+    pub fn file_info(&self, folder: &str, name: &str) -> Result<CameraFileInfo, AppError> {
+        let (folder_cstr, name_cstr) = (c_string(folder)?, c_string(name)?);
+        // SAFETY: plain C struct, valid when zeroed
+        let mut info: gphoto2_sys::CameraFileInfo = unsafe { std::mem::zeroed() };
+        // SAFETY: all pointers are valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_file_get_info(
+                    self.as_ptr(),
+                    folder_cstr.as_ptr(),
+                    name_cstr.as_ptr(),
+                    &mut info,
+                    self.context.as_ptr(),
+                )
+            },
+            &format!("Failed to get info for {}/{}", folder, name),
+        )?;
+
+        let fields = info.file.fields;
+        let has = |field| fields & field != 0;
+        Ok(CameraFileInfo {
+            size: has(gphoto2_sys::GP_FILE_INFO_SIZE).then_some(info.file.size),
+            mime_type: has(gphoto2_sys::GP_FILE_INFO_TYPE)
+                .then(|| {
+                    // SAFETY: libgphoto2 NUL-terminates the fixed-size buffer
+                    unsafe { CStr::from_ptr(info.file.mime_type.as_ptr()) }
+                        .to_str()
+                        .ok()
+                        .map(str::to_string)
+                })
+                .flatten(),
+            mtime: has(gphoto2_sys::GP_FILE_INFO_MTIME).then_some(info.file.mtime as i64),
+        })
+    }
+
+    /// Reads part of a file into `buf` starting at `offset`. Returns the
+    /// number of bytes read, which is 0 at the end of the file.
+    pub fn read(
+        &self,
+        folder: &str,
+        name: &str,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, AppError> {
+        let (folder_cstr, name_cstr) = (c_string(folder)?, c_string(name)?);
+        let mut size = buf.len() as u64;
+        // SAFETY: `buf` is valid for `size` bytes and all other pointers are
+        // valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_file_read(
+                    self.as_ptr(),
+                    folder_cstr.as_ptr(),
+                    name_cstr.as_ptr(),
+                    gphoto2_sys::GP_FILE_TYPE_NORMAL,
+                    offset,
+                    buf.as_mut_ptr() as *mut c_char,
+                    &mut size,
+                    self.context.as_ptr(),
+                )
+            },
+            &format!("Failed to read {}/{}", folder, name),
+        )?;
+        Ok((size as usize).min(buf.len()))
+    }
+
+    /// Downloads a whole file into `dest`.
+    #[cfg(unix)]
+    pub fn download_to(&self, folder: &str, name: &str, dest: &File) -> Result<(), AppError> {
+        let (folder_cstr, name_cstr) = (c_string(folder)?, c_string(name)?);
+        let file = CameraFile::to_file(dest)?;
+        // SAFETY: all pointers are valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_file_get(
+                    self.as_ptr(),
+                    folder_cstr.as_ptr(),
+                    name_cstr.as_ptr(),
+                    gphoto2_sys::GP_FILE_TYPE_NORMAL,
+                    file.as_ptr(),
+                    self.context.as_ptr(),
+                )
+            },
+            &format!("Failed to download {}/{}", folder, name),
+        )
+    }
+
+    pub fn delete_file(&self, folder: &str, name: &str) -> Result<(), AppError> {
+        let (folder_cstr, name_cstr) = (c_string(folder)?, c_string(name)?);
+        // SAFETY: all pointers are valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_file_delete(
+                    self.as_ptr(),
+                    folder_cstr.as_ptr(),
+                    name_cstr.as_ptr(),
+                    self.context.as_ptr(),
+                )
+            },
+            &format!("Failed to delete {}/{}", folder, name),
+        )
+    }
+
+    /// Human-readable summary of the camera. For PTP cameras it includes the
+    /// model, firmware version and serial number from the PTP DeviceInfo.
+    pub fn summary(&self) -> Result<String, AppError> {
+        // SAFETY: plain C struct, valid when zeroed
+        let mut summary: Box<gphoto2_sys::CameraText> = Box::new(unsafe { std::mem::zeroed() });
+        // SAFETY: all pointers are valid for the duration of the call
+        check(
+            unsafe {
+                gphoto2_sys::gp_camera_get_summary(
+                    self.as_ptr(),
+                    &mut *summary,
+                    self.context.as_ptr(),
+                )
+            },
+            "Failed to read camera summary",
+        )?;
+
+        // SAFETY: libgphoto2 NUL-terminates the fixed-size buffer
+        Ok(unsafe { CStr::from_ptr(summary.text.as_ptr()) }
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    fn as_ptr(&self) -> *mut gphoto2_sys::Camera {
+        self.camera.as_ptr()
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        // SAFETY: the camera was initialized with this context and we own the
+        // only reference. The context itself is dropped after this runs.
+        unsafe {
+            gphoto2_sys::gp_camera_exit(self.as_ptr(), self.context.as_ptr());
+            gphoto2_sys::gp_camera_unref(self.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use crate::camera::backend::{split_camera_path, CameraBackend};
+use crate::camera::pub_device_identity::DeviceIdentity;
+use crate::camera::pub_file_info::FileInfo;
+use crate::camera::usb::UsbDevice;
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+
+/// Only one gphoto2 process can claim the camera at a time.
+static GPHOTO2_LOCK: Mutex<()> = Mutex::new(());
+
+/// The gphoto2 CLI, looked up on `PATH`.
+const GPHOTO2: &str = "gphoto2";
+
+/// Talks to the camera over PTP by shelling out to the `gphoto2` CLI.
+///
+/// The CLI addresses files by their number within a folder, so every file
+/// operation first looks that number up. It can only fetch whole files, so
+/// uploads always stage them with `download`.
+#[derive(Debug)]
+pub struct Gphoto2CliBackend {
+    /// Port of the camera, such as `usb:001,007`, passed to every command
+    port: String,
+    /// The gphoto2 executable
+    program: PathBuf,
+}
+
+impl CameraBackend for Gphoto2CliBackend {
+    fn detect(device: &UsbDevice) -> Result<Self, AppError> {
+        println!("Attempting PTP camera access via gphoto2 CLI...");
+
+        // First, check if gphoto2 is available
+        let check_gphoto2 = Command::new("which")
+            .arg("gphoto2")
+            .output();
+
+        if check_gphoto2.is_err() || !check_gphoto2.as_ref().unwrap().status.success() {
+            return Err(AppError::CameraOperation(
+                "gphoto2 CLI not found. Please install it with: brew install gphoto2".to_string(),
+            ));
+        }
+
+        // Try to detect cameras
+        let detect_output = gphoto2(Path::new(GPHOTO2), &["--auto-detect"])?;
+        let detect_stdout = String::from_utf8_lossy(&detect_output.stdout);
+
+        // Check if this camera was detected
+        let port = device.gphoto2_port();
+        let Some(line) = detect_stdout.lines().find(|line| line.contains(&port)) else {
+            return Err(AppError::CameraOperation(format!(
+                "No PTP camera detected by gphoto2 on {}",
+                port
+            )));
+        };
+
+        println!("Camera detected via gphoto2: {}", line.trim());
+        Ok(Self {
+            port,
+            program: PathBuf::from(GPHOTO2),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "gphoto2-cli"
+    }
+
+    fn identity(&self) -> DeviceIdentity {
+        match self.gphoto2(&["--summary"]) {
+            Ok(output) => DeviceIdentity::from_summary(&String::from_utf8_lossy(&output.stdout)),
+            Err(e) => {
+                eprintln!("Failed to read camera identity: {}", e);
+                DeviceIdentity::default()
+            }
+        }
+    }
+
+    fn list(&self) -> Result<Vec<FileInfo>, AppError> {
+        let list_output = self.gphoto2(&["--list-files"])?;
+        let list_stdout = String::from_utf8_lossy(&list_output.stdout);
+
+        // Parse file list from gphoto2 output
+        Ok(parse_gphoto2_file_list(&list_stdout)
+            .into_iter()
+            .map(|(_, info)| info)
+            .collect())
+    }
+
+    fn stat(&self, file: &Path) -> Result<FileInfo, AppError> {
+        let (folder, number, mut info) = self.find_file(file)?;
+        let output = self.gphoto2(&["--folder", &folder, "--show-info", &number])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        // The listing rounds sizes up to whole kilobytes, but the file section
+        // has the exact size on a line like "  Size:        8567806 byte(s)"
+        info.size = stdout
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Size:"))
+            .and_then(|size| size.split_whitespace().next())
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| {
+                AppError::CameraOperation(format!("gphoto2 reported no size for {}", file.display()))
+            })?;
+
+        Ok(info)
+    }
+
+    /// Fetches the whole file for every read; see `download`.
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+        let (folder, number) = self.file_number(file)?;
+        let output =
+            self.gphoto2(&["--quiet", "--folder", &folder, "--get-file", &number, "--stdout"])?;
+
+        let data = output.stdout;
+        let start = (offset as usize).min(data.len());
+        let end = (offset.saturating_add(len) as usize).min(data.len());
+        Ok(data[start..end].to_vec())
+    }
+
+    fn supports_partial_reads(&self) -> bool {
+        false
+    }
+
+    fn download(&self, file: &Path, dest: &Path) -> Result<u64, AppError> {
+        let (folder, number) = self.file_number(file)?;
+        // `--filename` expands `%` patterns; `%%` is a literal one
+        let dest_arg = dest.to_string_lossy().replace('%', "%%");
+        self.gphoto2(&[
+            "--quiet",
+            "--folder",
+            &folder,
+            "--get-file",
+            &number,
+            "--filename",
+            &dest_arg,
+            "--force-overwrite",
+        ])?;
+        Ok(std::fs::metadata(dest)?.len())
+    }
+
+    fn delete(&self, file: &Path) -> Result<(), AppError> {
+        let (folder, number) = self.file_number(file)?;
+        self.gphoto2(&["--folder", &folder, "--delete-file", &number])?;
+        Ok(())
+    }
+}
+
+/// Runs gphoto2, failing if it cannot start or exits unsuccessfully.
+fn gphoto2(program: &Path, args: &[&str]) -> Result<Output, AppError> {
+    let _guard = GPHOTO2_LOCK.lock().unwrap();
+    let output = Command::new(program).args(args).output().map_err(|e| {
+        AppError::CameraOperation(format!("Failed to run gphoto2 {}: {}", args.join(" "), e))
+    })?;
+
+    if !output.status.success() {
+        return Err(AppError::CameraOperation(format!(
+            "gphoto2 {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output)
+}
+
+impl Gphoto2CliBackend {
+    /// Runs gphoto2 against this backend's camera.
+    fn gphoto2(&self, args: &[&str]) -> Result<Output, AppError> {
+        let mut port_args = vec!["--port", self.port.as_str()];
+        port_args.extend_from_slice(args);
+        gphoto2(&self.program, &port_args)
+    }
+
+    /// Looks up the folder, CLI file number and listed metadata of `file`.
+    fn find_file(&self, file: &Path) -> Result<(String, String, FileInfo), AppError> {
+        let (folder, name) = split_camera_path(file)?;
+        let output = self.gphoto2(&["--folder", &folder, "--no-recurse", "--list-files"])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let (number, info) = parse_gphoto2_file_list(&stdout)
+            .into_iter()
+            .find(|(_, info)| info.filename == name)
+            .ok_or_else(|| {
+                AppError::CameraOperation(format!("{} not found on the camera", file.display()))
+            })?;
+
+        Ok((folder, number, info))
+    }
+
+    fn file_number(&self, file: &Path) -> Result<(String, String), AppError> {
+        let (folder, number, _) = self.find_file(file)?;
+        Ok((folder, number))
+    }
+}
+
+/// Parses `--list-files` output into each file's CLI number and metadata.
+fn parse_gphoto2_file_list(output: &str) -> Vec<(String, FileInfo)> {
+    let mut files = Vec::new();
+    let mut current_folder = String::new();
+
+    for line in output.lines() {
+        // Look for folder lines like "There are N files in folder '/path'."
+        // or "There is 1 file in folder '/path'."
+        if line.starts_with("There") && line.contains(" in folder ") {
+            if let Some(start) = line.find('\'') {
+                if let Some(end) = line[start + 1..].find('\'') {
+                    current_folder = line[start + 1..start + 1 + end].to_string();
+                }
+            }
+        }
+        // Look for file lines starting with #N
+        else if line.starts_with('#') {
+            if let Some(file) = parse_file_line(&current_folder, line) {
+                files.push(file);
+            }
+        }
+    }
+
+    files
+}
+
+/// Parses a file line like
+/// `#1     R0010001.JPG               rd  8367 KB 5376x2688 image/jpeg 1650000000`.
+/// Only the number and name are always there; the camera decides which of
+/// the other columns it fills in.
+fn parse_file_line(folder: &str, line: &str) -> Option<(String, FileInfo)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let number = parts.first()?.strip_prefix('#')?.to_string();
+    let filename = parts.get(1)?;
+    let file_path = if folder.is_empty() {
+        PathBuf::from(filename)
+    } else {
+        PathBuf::from(format!("{}/{}", folder, filename))
+    };
+
+    // Rounded up to whole kilobytes; `stat` has the exact size
+    let size_index = parts
+        .iter()
+        .position(|part| *part == "KB")
+        .filter(|i| *i > 2);
+    let size = size_index
+        .and_then(|i| parts[i - 1].parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+        .unwrap_or_default();
+
+    let columns = &parts[size_index.map_or(2, |i| i + 1)..];
+    let content_type = columns
+        .iter()
+        .find(|column| column.contains('/'))
+        .map(|column| column.to_string());
+    let mtime = columns.last().and_then(|column| column.parse().ok());
+
+    Some((number, FileInfo::new(file_path, size, content_type, mtime)))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A fake gphoto2 that logs its arguments and serves one 10-byte file.
+    fn fake_gphoto2(dir: &Path) -> Gphoto2CliBackend {
+        let script = format!(
+            r##"#!/bin/sh
+echo "$*" >> '{log}'
+case "$*" in
+  *--list-files*)
+    echo "There is 1 file in folder '/DCIM'."
+    echo "#1     R0010001.MP4               rd     1 KB video/mp4"
+    ;;
+  *--get-file*)
+    while [ "$1" != "--filename" ]; do shift; done
+    printf '0123456789' > "$2"
+    ;;
+esac
+"##,
+            log = dir.join("log").display()
+        );
+        let program = dir.join("gphoto2");
+        fs::write(&program, script).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+        Gphoto2CliBackend {
+            port: "usb:001,002".to_string(),
+            program,
+        }
+    }
+
+    fn fetches(dir: &Path) -> usize {
+        fs::read_to_string(dir.join("log"))
+            .unwrap()
+            .lines()
+            .filter(|line| line.contains("--get-file"))
+            .count()
+    }
+
+    #[test]
+    fn downloads_a_file_in_one_fetch() {
+        let dir = std::env::temp_dir().join(format!("altoid-gphoto2-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let backend = fake_gphoto2(&dir);
+        let dest = dir.join("R0010001.MP4");
+
+        assert!(!backend.supports_partial_reads());
+        let copied = backend
+            .download(Path::new("/DCIM/R0010001.MP4"), &dest)
+            .unwrap();
+
+        assert_eq!(copied, 10);
+        assert_eq!(fs::read(&dest).unwrap(), b"0123456789");
+        assert_eq!(fetches(&dir), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
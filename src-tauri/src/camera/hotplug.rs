@@ -0,0 +1,222 @@
+use crate::camera::camera::forget;
+use crate::camera::device_type::{CameraInfo, CAMERAS};
+use crate::camera::simulated;
+use crate::camera::usb::{device_at, UsbDevice};
+use rusb::{Context, Device, DeviceDescriptor, Hotplug, HotplugBuilder, Registration, UsbContext};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+// Event names the frontend listens for through `CameraService`
+pub const CAMERA_CONNECTED_EVENT: &str = "camera-connected";
+pub const CAMERA_DISCONNECTED_EVENT: &str = "camera-disconnected";
+
+/// How often USB devices are enumerated when libusb has no hotplug support.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often a device that just arrived is read before giving up. It may not
+/// be enumerated or openable yet right after the hotplug event.
+const ARRIVAL_ATTEMPTS: u32 = 4;
+
+/// Wait before the second read of a device that just arrived, doubled for
+/// every read after that.
+const ARRIVAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Payload of the camera events. Mirrors `src/rust-api/model/CameraEvent.ts`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraEvent {
+    pub usb_id: String,
+    pub info: &'static CameraInfo,
+    pub usb: UsbDevice,
+}
+
+/// Bus number and address of a USB device. Stays the same until it is
+/// unplugged, so it also identifies the device once it is gone.
+type Port = (u8, u8);
+
+/// USB vendor and product id of a device.
+type Ids = (u16, u16);
+
+enum UsbEvent {
+    Arrived(Port, Ids),
+    Left(Port),
+    /// Every device currently plugged in
+    Present(HashMap<Port, Ids>),
+}
+
+/// Watches USB for supported cameras coming and going, and emits
+/// `camera-connected` / `camera-disconnected` for each.
+///
+/// Uses libusb hotplug callbacks where the platform has them and falls back
+/// to polling otherwise. Cameras already plugged in are reported on start.
+pub fn start(app: AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        if simulated::is_enabled() {
+            // Simulated cameras stay plugged in for the whole run
+            let present = simulated::cameras()
+                .into_iter()
+                .map(|(usb, _)| ((usb.bus, usb.address), (usb.vendor_id, usb.product_id)))
+                .collect();
+            let _ = sender.send(UsbEvent::Present(present));
+            return;
+        }
+        if rusb::has_hotplug() {
+            match watch_hotplug(sender.clone()) {
+                Ok(()) => return,
+                Err(e) => eprintln!("USB hotplug unavailable ({}), polling instead", e),
+            }
+        }
+        poll(sender);
+    });
+
+    thread::spawn(move || dispatch(app, receiver));
+}
+
+struct HotplugForwarder(Sender<UsbEvent>);
+
+// Callbacks run inside libusb, so they only forward events to `dispatch`
+impl<T: UsbContext> Hotplug<T> for HotplugForwarder {
+    fn device_arrived(&mut self, device: Device<T>) {
+        // Descriptors are cached by libusb, so reading them here does no I/O
+        if let Ok(desc) = device.device_descriptor() {
+            let _ = self.0.send(UsbEvent::Arrived(port(&device), ids(&desc)));
+        }
+    }
+
+    fn device_left(&mut self, device: Device<T>) {
+        let _ = self.0.send(UsbEvent::Left(port(&device)));
+    }
+}
+
+/// Runs libusb's event loop for the hotplug callbacks. Only returns when
+/// hotplug cannot be set up or stops working.
+fn watch_hotplug(sender: Sender<UsbEvent>) -> rusb::Result<()> {
+    let context = Context::new()?;
+    let _registration: Registration<Context> = HotplugBuilder::new()
+        .enumerate(true)
+        .register(&context, Box::new(HotplugForwarder(sender)))?;
+
+    loop {
+        context.handle_events(None)?;
+    }
+}
+
+fn poll(sender: Sender<UsbEvent>) {
+    loop {
+        match rusb::devices() {
+            Ok(devices) => {
+                let present = devices
+                    .iter()
+                    .filter_map(|device| {
+                        let desc = device.device_descriptor().ok()?;
+                        Some((port(&device), ids(&desc)))
+                    })
+                    .collect();
+                if sender.send(UsbEvent::Present(present)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => eprintln!("Failed to enumerate USB devices: {}", e),
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Tracks which cameras are plugged in and emits an event for each change.
+fn dispatch(app: AppHandle, events: Receiver<UsbEvent>) {
+    let mut cameras: HashMap<Port, CameraEvent> = HashMap::new();
+
+    for event in events {
+        match event {
+            UsbEvent::Arrived(port, ids) => arrived(&app, &mut cameras, port, ids),
+            UsbEvent::Left(port) => left(&app, &mut cameras, port),
+            UsbEvent::Present(present) => {
+                let gone: Vec<Port> = cameras
+                    .keys()
+                    .filter(|port| !present.contains_key(port))
+                    .copied()
+                    .collect();
+                for port in gone {
+                    left(&app, &mut cameras, port);
+                }
+                for (port, ids) in present {
+                    arrived(&app, &mut cameras, port, ids);
+                }
+            }
+        }
+    }
+}
+
+fn arrived(app: &AppHandle, cameras: &mut HashMap<Port, CameraEvent>, port: Port, ids: Ids) {
+    if cameras.contains_key(&port) || !CAMERAS.has_vendor(ids.0) {
+        return;
+    }
+    // Read here rather than in the hotplug callback, which must not open devices
+    let Some(usb) = read_arrived(port) else {
+        eprintln!(
+            "Camera at bus {}, address {} could not be read",
+            port.0, port.1
+        );
+        return;
+    };
+    let Some(info) = CAMERAS.get(usb.vendor_id, usb.product_id, usb.product.as_deref()) else {
+        return;
+    };
+
+    let camera = CameraEvent {
+        usb_id: usb.id(),
+        info,
+        usb,
+    };
+    emit(app, CAMERA_CONNECTED_EVENT, &camera);
+    cameras.insert(port, camera);
+}
+
+/// Reads a device that just arrived, retrying with a backoff until it is
+/// enumerated and its descriptor strings can be read. Gives up on the
+/// strings after `ARRIVAL_ATTEMPTS` reads, as some devices have none.
+fn read_arrived(port: Port) -> Option<UsbDevice> {
+    let mut backoff = ARRIVAL_BACKOFF;
+    for attempt in 1..=ARRIVAL_ATTEMPTS {
+        let usb = device_at(port.0, port.1);
+        let complete = usb
+            .as_ref()
+            .is_some_and(|usb| usb.serial.is_some() || usb.product.is_some());
+        if complete || attempt == ARRIVAL_ATTEMPTS {
+            return usb;
+        }
+        thread::sleep(backoff);
+        backoff *= 2;
+    }
+    None
+}
+
+fn left(app: &AppHandle, cameras: &mut HashMap<Port, CameraEvent>, port: Port) {
+    let Some(camera) = cameras.remove(&port) else {
+        return;
+    };
+
+    // Its connection is dead; the next access must detect the camera again
+    forget(&camera.usb_id);
+    emit(app, CAMERA_DISCONNECTED_EVENT, &camera);
+}
+
+fn emit(app: &AppHandle, event: &str, camera: &CameraEvent) {
+    if let Err(e) = app.emit(event, camera) {
+        eprintln!("Failed to emit {} for {}: {}", event, camera.usb_id, e);
+    }
+}
+
+fn ids(desc: &DeviceDescriptor) -> Ids {
+    (desc.vendor_id(), desc.product_id())
+}
+
+fn port<T: UsbContext>(device: &Device<T>) -> Port {
+    (device.bus_number(), device.address())
+}
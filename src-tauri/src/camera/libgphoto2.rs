@@ -0,0 +1,365 @@
+use crate::cache::settings_cache::get_settings;
+use crate::camera::backend::{split_camera_path, CameraBackend};
+use crate::camera::gphoto::Camera;
+use crate::camera::pub_device_identity::DeviceIdentity;
+use crate::camera::pub_file_info::FileInfo;
+use crate::camera::usb::UsbDevice;
+use crate::error::AppError;
+use crate::upload::staging::staging_dir;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Talks to the camera over PTP through libgphoto2 (`gphoto2-sys`).
+///
+/// One connection is kept open and shared by every operation: a PTP camera
+/// can only be claimed once, so concurrent uploads take turns on the lock.
+/// After a failed operation the connection is reopened on next use, in case
+/// the camera was unplugged in between.
+///
+/// Drivers that cannot read part of a file get it downloaded whole, once,
+/// to the staging directory; the reads that follow are served from there.
+/// A copy is kept for each file being uploaded, see `Downloads`.
+#[derive(Debug)]
+pub struct LibGphoto2Backend {
+    /// Port of the camera, such as `usb:001,007`
+    port: String,
+    session: Mutex<Option<Camera>>,
+    /// Files downloaded whole
+    downloads: Mutex<Downloads>,
+}
+
+impl LibGphoto2Backend {
+    fn with_camera<T>(
+        &self,
+        operation: impl FnOnce(&Camera) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let mut session = self.session.lock().unwrap();
+        let camera = match session.take() {
+            Some(camera) => camera,
+            None => Camera::open(&self.port)?,
+        };
+
+        let result = operation(&camera);
+        if result.is_ok() {
+            *session = Some(camera);
+        }
+        result
+    }
+
+    /// Reads from the local copy of `file`, downloading it first if there
+    /// is none.
+    fn download_and_read(
+        &self,
+        camera: &Camera,
+        file: &Path,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, AppError> {
+        // Only called with the camera locked, so nobody else is downloading
+        let download = match self.downloads.lock().unwrap().get(file) {
+            Some(download) => download,
+            None => {
+                let download = Arc::new(Download::new(camera, &self.port, file)?);
+                let capacity = get_settings().max_concurrent_files;
+                self.downloads
+                    .lock()
+                    .unwrap()
+                    .insert(download.clone(), capacity);
+                download
+            }
+        };
+        download.read(offset, len)
+    }
+
+    /// Reads from the local copy of `file`, if there is one.
+    fn read_downloaded(
+        &self,
+        file: &Path,
+        offset: u64,
+        len: u64,
+    ) -> Option<Result<Vec<u8>, AppError>> {
+        let download = self.downloads.lock().unwrap().get(file)?;
+        Some(download.read(offset, len))
+    }
+}
+
+impl CameraBackend for LibGphoto2Backend {
+    fn detect(device: &UsbDevice) -> Result<Self, AppError> {
+        let port = device.gphoto2_port();
+        println!("Attempting PTP camera access via gphoto2-sys on {}...", port);
+        let camera = Camera::open(&port)?;
+        println!("Successfully connected to camera via gphoto2-sys");
+        Ok(Self {
+            port,
+            session: Mutex::new(Some(camera)),
+            downloads: Mutex::new(Downloads::default()),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "libgphoto2"
+    }
+
+    fn identity(&self) -> DeviceIdentity {
+        match self.with_camera(|camera| camera.summary()) {
+            Ok(summary) => DeviceIdentity::from_summary(&summary),
+            Err(e) => {
+                eprintln!("Failed to read camera identity: {}", e);
+                DeviceIdentity::default()
+            }
+        }
+    }
+
+    fn list(&self) -> Result<Vec<FileInfo>, AppError> {
+        self.with_camera(|camera| list_files_recursive(camera, "/"))
+    }
+
+    fn stat(&self, file: &Path) -> Result<FileInfo, AppError> {
+        let (folder, name) = split_camera_path(file)?;
+        self.with_camera(|camera| file_info(camera, &folder, &name))
+    }
+
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+        if let Some(result) = self.read_downloaded(file, offset, len) {
+            return result;
+        }
+        let (folder, name) = split_camera_path(file)?;
+
+        self.with_camera(|camera| {
+            let mut buf = vec![0u8; len as usize];
+            let mut filled = 0;
+
+            // The camera may return less than asked for; keep reading until
+            // the buffer is full or the file ends
+            while filled < buf.len() {
+                let read = match camera.read(&folder, &name, offset + filled as u64, &mut buf[filled..]) {
+                    Ok(read) => read,
+                    Err(e) if filled == 0 => {
+                        // Not every driver supports partial reads
+                        eprintln!("Partial read of {} failed ({}), downloading it whole", file.display(), e);
+                        return self.download_and_read(camera, file, offset, len);
+                    }
+                    Err(e) => return Err(e),
+                };
+                if read == 0 {
+                    break;
+                }
+                filled += read;
+            }
+
+            buf.truncate(filled);
+            Ok(buf)
+        })
+    }
+
+    fn delete(&self, file: &Path) -> Result<(), AppError> {
+        let (folder, name) = split_camera_path(file)?;
+        self.with_camera(|camera| camera.delete_file(&folder, &name))?;
+
+        self.downloads.lock().unwrap().remove(file);
+        Ok(())
+    }
+}
+
+/// Local copies of files downloaded whole, least recently used first.
+///
+/// Keeps as many as files are uploaded at once, so concurrent uploads from
+/// one camera do not evict each other's copies and download them again. A
+/// copy evicted while it is read is removed once its last reader is done.
+#[derive(Debug, Default)]
+struct Downloads(Vec<Arc<Download>>);
+
+impl Downloads {
+    /// The copy of `file`, marked as used most recently.
+    fn get(&mut self, file: &Path) -> Option<Arc<Download>> {
+        let index = self.0.iter().position(|download| download.file == file)?;
+        let download = self.0.remove(index);
+        self.0.push(download.clone());
+        Some(download)
+    }
+
+    /// Adds a copy, evicting the least recently used beyond `capacity`.
+    fn insert(&mut self, download: Arc<Download>, capacity: usize) {
+        self.remove(&download.file);
+        self.0.push(download);
+        let excess = self.0.len().saturating_sub(capacity.max(1));
+        self.0.drain(..excess);
+    }
+
+    fn remove(&mut self, file: &Path) {
+        self.0.retain(|download| download.file != file);
+    }
+}
+
+/// Local copy of a file downloaded whole, removed when dropped.
+#[derive(Debug)]
+struct Download {
+    /// Path of the file on the camera
+    file: PathBuf,
+    /// Where the copy is kept
+    path: PathBuf,
+}
+
+impl Download {
+    #[cfg(unix)]
+    fn new(camera: &Camera, port: &str, file: &Path) -> Result<Self, AppError> {
+        let (folder, name) = split_camera_path(file)?;
+        // Named after the camera and the file, so copies never share a path
+        let copy_name: String = format!("gphoto2-{}-{}", port, file.display())
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        fs::create_dir_all(staging_dir())?;
+
+        let download = Self {
+            file: file.to_path_buf(),
+            path: staging_dir().join(copy_name),
+        };
+        camera.download_to(&folder, &name, &File::create(&download.path)?)?;
+        Ok(download)
+    }
+
+    #[cfg(not(unix))]
+    fn new(_camera: &Camera, _port: &str, file: &Path) -> Result<Self, AppError> {
+        Err(AppError::CameraOperation(format!(
+            "Camera cannot read part of {}, and downloading it whole is not supported here",
+            file.display()
+        )))
+    }
+
+    fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+        // A handle per read, as several uploads may read the copy at once
+        let mut copy = File::open(&self.path)?;
+        copy.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::with_capacity(len as usize);
+        copy.take(len).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            eprintln!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Metadata of one file, as `gp_camera_file_get_info` reports it.
+fn file_info(camera: &Camera, folder: &str, name: &str) -> Result<FileInfo, AppError> {
+    let path = PathBuf::from(folder).join(name);
+    let info = camera.file_info(folder, name)?;
+    let size = info.size.ok_or_else(|| {
+        AppError::CameraOperation(format!("Camera reported no size for {}", path.display()))
+    })?;
+    let mtime = info.mtime.and_then(|mtime| u64::try_from(mtime).ok());
+
+    Ok(FileInfo::new(path, size, info.mime_type, mtime))
+}
+
+fn list_files_recursive(camera: &Camera, folder: &str) -> Result<Vec<FileInfo>, AppError> {
+    let child = |name: &str| {
+        if folder == "/" {
+            format!("/{}", name)
+        } else {
+            format!("{}/{}", folder, name)
+        }
+    };
+
+    let mut all_files = camera
+        .list_files(folder)?
+        .iter()
+        .map(|name| file_info(camera, folder, name))
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    for name in camera.list_folders(folder)? {
+        let subfolder_path = child(&name);
+
+        // Recursively list files in the subfolder
+        match list_files_recursive(camera, &subfolder_path) {
+            Ok(mut subfiles) => all_files.append(&mut subfiles),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Could not list files in subfolder {}: {}",
+                    subfolder_path, e
+                );
+            }
+        }
+    }
+
+    Ok(all_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(dir: &Path, file: &str) -> Arc<Download> {
+        let path = dir.join(file);
+        fs::write(&path, file).unwrap();
+        Arc::new(Download {
+            file: PathBuf::from(file),
+            path,
+        })
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("altoid-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn concurrent_files_keep_their_copies() {
+        let dir = temp_dir("downloads-concurrent");
+        let mut downloads = Downloads::default();
+        downloads.insert(download(&dir, "A.MP4"), 2);
+        downloads.insert(download(&dir, "B.MP4"), 2);
+
+        // Parts of both files are read in turns
+        for _ in 0..3 {
+            for file in ["A.MP4", "B.MP4"] {
+                let copy = downloads.get(Path::new(file)).unwrap();
+                assert_eq!(copy.read(0, 5).unwrap(), file.as_bytes());
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_copy() {
+        let dir = temp_dir("downloads-evict");
+        let mut downloads = Downloads::default();
+        downloads.insert(download(&dir, "A.MP4"), 2);
+        downloads.insert(download(&dir, "B.MP4"), 2);
+        downloads.get(Path::new("A.MP4"));
+        downloads.insert(download(&dir, "C.MP4"), 2);
+
+        assert!(downloads.get(Path::new("B.MP4")).is_none());
+        assert!(!dir.join("B.MP4").exists());
+        assert!(downloads.get(Path::new("A.MP4")).is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_an_evicted_copy_until_its_reader_is_done() {
+        let dir = temp_dir("downloads-reader");
+        let mut downloads = Downloads::default();
+        downloads.insert(download(&dir, "A.MP4"), 1);
+        let reader = downloads.get(Path::new("A.MP4")).unwrap();
+        downloads.insert(download(&dir, "B.MP4"), 1);
+
+        assert_eq!(reader.read(0, 5).unwrap(), b"A.MP4");
+        drop(reader);
+        assert!(!dir.join("A.MP4").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
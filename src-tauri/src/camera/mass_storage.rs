@@ -0,0 +1,288 @@
+use crate::camera::backend::CameraBackend;
+use crate::camera::camera::is_claimed;
+use crate::camera::pub_device_identity::DeviceIdentity;
+use crate::camera::pub_file_info::FileInfo;
+use crate::camera::usb::{connected_cameras, UsbDevice};
+use crate::error::AppError;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Reads the camera's card as a mounted removable drive.
+#[derive(Debug)]
+pub struct MassStorageBackend {
+    mount_point: PathBuf,
+}
+
+impl MassStorageBackend {
+    pub fn new(mount_point: PathBuf) -> Self {
+        Self { mount_point }
+    }
+
+    fn path_of(&self, file: &Path) -> PathBuf {
+        self.mount_point.join(file)
+    }
+}
+
+impl CameraBackend for MassStorageBackend {
+    fn detect(device: &UsbDevice) -> Result<Self, AppError> {
+        println!("Trying mass storage detection...");
+
+        // Try to enumerate mounted drives
+        let drives = bb_drivelist::drive_list().map_err(|e| {
+            AppError::CameraOperation(format!("Failed to enumerate drives: {}", e))
+        })?;
+
+        println!("Total drives detected: {}", drives.len());
+
+        // Look for removable drives (cameras typically mount as removable storage)
+        for drive in &drives {
+            println!("Drive: device={}, is_removable={}, mountpoints={}",
+                     drive.device, drive.is_removable, drive.mountpoints.len());
+
+            for mp in &drive.mountpoints {
+                println!("  Mountpoint: {}", mp.path);
+            }
+        }
+
+        // A drive that cannot be traced to its USB device could be the card
+        // of any camera, so it is only used when there is just one
+        let sole_camera = connected_cameras().len() == 1;
+
+        // First, try removable drives
+        for drive in &drives {
+            if !drive.is_removable || !is_drive_of(&drive.device, device, sole_camera) {
+                continue;
+            }
+
+            // Try each mount point, leaving those of other cameras alone
+            for mount_point in &drive.mountpoints {
+                let path = PathBuf::from(&mount_point.path);
+                if is_claimed(&path, &device.id()) {
+                    continue;
+                }
+                println!("Checking removable drive at: {}", path.display());
+
+                match list_files_recursive(&path, &path) {
+                    Ok(files) if !files.is_empty() => return Ok(Self::new(path)),
+                    Ok(_) => {
+                        println!("No files found at {}", path.display());
+                    }
+                    Err(e) => {
+                        return Err(AppError::CameraOperation(format!(
+                            "Cannot access files at {}. Error: {}. \
+                            On macOS, you may need to grant 'Full Disk Access' permission to your terminal or app in System Settings > Privacy & Security > Full Disk Access.",
+                            path.display(),
+                            e
+                        )));
+                    }
+                }
+            }
+        }
+
+        // On macOS, RICOH THETA cameras might mount at /Volumes/
+        // Try common RICOH THETA mount points
+        let potential_paths = vec![
+            "/Volumes/RICOH THETA",
+            "/Volumes/RICOH THETA Z1",
+            "/Volumes/NO NAME",
+        ];
+
+        for path_str in potential_paths {
+            let path = PathBuf::from(path_str);
+            if sole_camera && path.exists() && !is_claimed(&path, &device.id()) {
+                println!("Found potential RICOH mount at: {}", path.display());
+                match list_files_recursive(&path, &path) {
+                    Ok(files) if !files.is_empty() => return Ok(Self::new(path)),
+                    Ok(_) => {
+                        println!("No files found at {}", path.display());
+                    }
+                    Err(e) => {
+                        eprintln!("Error accessing {}: {}", path.display(), e);
+                    }
+                }
+            }
+        }
+
+        if !sole_camera && !cfg!(target_os = "linux") {
+            return Err(AppError::CameraOperation(
+                "Cannot tell which mounted drive belongs to which camera while several are connected. Connect one camera at a time to read it as a drive.".to_string(),
+            ));
+        }
+        Err(AppError::CameraOperation(
+            "Camera found but no mounted storage device detected. Please ensure the camera is in the correct USB mode (usually 'Mass Storage' or 'File Transfer' mode).".to_string(),
+        ))
+    }
+
+    fn name(&self) -> &'static str {
+        "mass-storage"
+    }
+
+    fn mount_point(&self) -> Option<&Path> {
+        Some(&self.mount_point)
+    }
+
+    fn identity(&self) -> DeviceIdentity {
+        let volume_id = volume_uuid(&self.mount_point)
+            .map(|uuid| hex::encode(Sha256::digest(uuid.as_bytes()))[..16].to_string());
+        if volume_id.is_none() {
+            eprintln!("Could not read the volume UUID of {}", self.mount_point.display());
+        }
+
+        DeviceIdentity {
+            volume_id,
+            ..DeviceIdentity::default()
+        }
+    }
+
+    fn list(&self) -> Result<Vec<FileInfo>, AppError> {
+        list_files_recursive(&self.mount_point, &self.mount_point)?
+            .iter()
+            .map(|file| self.stat(file))
+            .collect()
+    }
+
+    fn stat(&self, file: &Path) -> Result<FileInfo, AppError> {
+        let metadata = fs::metadata(self.path_of(file))?;
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_secs());
+
+        Ok(FileInfo::new(file.to_path_buf(), metadata.len(), None, mtime))
+    }
+
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+        let mut file = File::open(self.path_of(file))?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::with_capacity(len as usize);
+        file.take(len).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn delete(&self, file: &Path) -> Result<(), AppError> {
+        let path = self.path_of(file);
+        fs::remove_file(&path).map_err(|e| {
+            AppError::CameraOperation(format!("Failed to delete {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// UUID of the volume mounted at `mount_point`, which lasts until the card
+/// is formatted.
+#[cfg(target_os = "linux")]
+fn volume_uuid(mount_point: &Path) -> Option<String> {
+    // Find the device mounted there; spaces in mount points are octal escaped
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    let device = mounts.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let device = fields.next()?;
+        let mounted_at = fields.next()?.replace("\\040", " ");
+        (Path::new(&mounted_at) == mount_point).then(|| PathBuf::from(device))
+    })?;
+    let device = fs::canonicalize(device).ok()?;
+
+    // udev links every volume with a UUID from /dev/disk/by-uuid
+    fs::read_dir("/dev/disk/by-uuid")
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|link| fs::canonicalize(link.path()).is_ok_and(|target| target == device))
+        .map(|link| link.file_name().to_string_lossy().into_owned())
+}
+
+#[cfg(target_os = "macos")]
+fn volume_uuid(mount_point: &Path) -> Option<String> {
+    let output = std::process::Command::new("diskutil")
+        .arg("info")
+        .arg(mount_point)
+        .output()
+        .ok()?;
+
+    // A line like "   Volume UUID:               0E239BC6-F960-3107-89CF-1C97F78BB46B"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Volume UUID:"))
+        .map(|uuid| uuid.trim().to_string())
+        .filter(|uuid| !uuid.is_empty())
+}
+
+#[cfg(target_os = "windows")]
+fn volume_uuid(mount_point: &Path) -> Option<String> {
+    let output = std::process::Command::new("mountvol")
+        .arg(mount_point)
+        .arg("/L")
+        .output()
+        .ok()?;
+
+    // Prints the volume name, like "\\?\Volume{4c1b02c1-d990-11dc-99ae-806e6f6e6963}\"
+    let name = String::from_utf8_lossy(&output.stdout);
+    let start = name.find('{')? + 1;
+    let end = name[start..].find('}')? + start;
+    Some(name[start..end].to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn volume_uuid(_mount_point: &Path) -> Option<String> {
+    None
+}
+
+/// Whether the drive at `drive_device` (such as `/dev/sdb`) is the card of
+/// `device`. The drive's sysfs path runs through the USB device it belongs
+/// to, named like `1-2.3`. Without one, as outside Linux, the drive is only
+/// taken for the card of the `sole_camera` connected.
+fn is_drive_of(drive_device: &str, device: &UsbDevice, sole_camera: bool) -> bool {
+    match drive_sysfs_path(drive_device) {
+        Some(sysfs_path) => {
+            let usb_name = format!("{}-{}", device.bus, device.port_path());
+            sysfs_path
+                .components()
+                .any(|component| component.as_os_str() == usb_name.as_str())
+        }
+        None => sole_camera,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn drive_sysfs_path(drive_device: &str) -> Option<PathBuf> {
+    let name = Path::new(drive_device).file_name()?;
+    fs::canonicalize(Path::new("/sys/block").join(name)).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drive_sysfs_path(_drive_device: &str) -> Option<PathBuf> {
+    None
+}
+
+fn list_files_recursive(base_path: &PathBuf, current_path: &PathBuf) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    let entries = fs::read_dir(current_path)?;
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            // Recursively list files in subdirectories
+            match list_files_recursive(base_path, &path) {
+                Ok(mut subfiles) => files.append(&mut subfiles),
+                Err(e) => {
+                    eprintln!("Warning: Could not read directory {}: {}", path.display(), e);
+                    // Continue with other directories
+                }
+            }
+        } else if path.is_file() {
+            // Store relative path from base mount point
+            if let Ok(relative_path) = path.strip_prefix(base_path) {
+                files.push(relative_path.to_path_buf());
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
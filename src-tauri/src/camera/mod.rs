@@ -1,3 +1,13 @@
 pub mod device_type;
 pub mod camera;
-pub mod camera2;
+pub mod backend;
+pub mod gphoto;
+pub mod gphoto2_cli;
+pub mod hotplug;
+pub mod libgphoto2;
+pub mod mass_storage;
+pub mod pub_device_identity;
+pub mod pub_file_info;
+pub mod simulated;
+pub mod stream;
+pub mod usb;
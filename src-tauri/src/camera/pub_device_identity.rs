@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// What a camera reports about itself, as far as its backend can read it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceIdentity {
+    pub serial: Option<String>,
+    pub firmware: Option<String>,
+    pub model: Option<String>,
+    /// Hash of the card's volume UUID, for cameras read as mass storage
+    pub volume_id: Option<String>,
+}
+
+impl DeviceIdentity {
+    /// Parses the summary libgphoto2 and `gphoto2 --summary` print for PTP
+    /// cameras, whose DeviceInfo section has lines like
+    /// `Model: RICOH THETA Z1`, `  Version: 1.60.1` and
+    /// `  Serial Number: 10100123`.
+    pub fn from_summary(summary: &str) -> Self {
+        let field = |name: &str| {
+            summary.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                let value = value.trim();
+                (key.trim() == name && !value.is_empty()).then(|| value.to_string())
+            })
+        };
+
+        Self {
+            serial: field("Serial Number"),
+            firmware: field("Version").or_else(|| field("Device Version")),
+            model: field("Model"),
+            volume_id: None,
+        }
+    }
+}
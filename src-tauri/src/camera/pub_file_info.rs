@@ -0,0 +1,65 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A file on the camera, as listed by its backend. Serialized to match
+/// `src/rust-api/model/FileInfo.ts`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileInfo {
+    /// Path the backend reads the file by
+    pub path: PathBuf,
+    pub filename: String,
+    /// Folder on the camera holding the file, such as `DCIM/100RICOH`
+    pub folder: String,
+    pub size: u64,
+    pub content_type: String,
+    /// Last modification, in seconds since the Unix epoch, if the camera keeps it
+    pub mtime: Option<u64>,
+    /// Shared by the files of a capture saved as one file per lens, see
+    /// `StorageLayout::capture_key`
+    pub capture: Option<String>,
+}
+
+impl FileInfo {
+    /// Content type falls back to a guess from the extension when the camera
+    /// does not report one.
+    pub fn new(path: PathBuf, size: u64, content_type: Option<String>, mtime: Option<u64>) -> Self {
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let folder = path
+            .parent()
+            .map(|folder| folder.display().to_string())
+            .unwrap_or_default();
+        let content_type = content_type.unwrap_or_else(|| content_type_for(&path).to_string());
+
+        Self {
+            path,
+            filename,
+            folder,
+            size,
+            content_type,
+            mtime,
+            capture: None,
+        }
+    }
+}
+
+/// Best-effort content type from the file extension.
+pub fn content_type_for(file: &Path) -> &'static str {
+    let extension = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("dng") => "image/x-adobe-dng",
+        Some("insp") => "image/jpeg",
+        Some("mp4") | Some("insv") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,418 @@
+use crate::camera::backend::CameraBackend;
+use crate::camera::device_type::{CameraInfo, CAMERAS};
+use crate::camera::mass_storage::MassStorageBackend;
+use crate::camera::pub_device_identity::DeviceIdentity;
+use crate::camera::pub_file_info::FileInfo;
+use crate::camera::usb::UsbDevice;
+use crate::error::AppError;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Environment variable that replaces the USB cameras with simulated ones.
+/// Holds fixture paths separated like `PATH`: each one is a fixture
+/// manifest (JSON) or a directory whose files make up the card.
+pub const SIMULATED_CAMERA_VAR: &str = "ALTOID_SIMULATED_CAMERA";
+
+/// Bus simulated cameras sit on. Each fixture gets the address of its
+/// position in `SIMULATED_CAMERA_VAR`, starting at 1.
+const SIMULATED_BUS: u8 = 0;
+
+/// Fixtures named by `SIMULATED_CAMERA_VAR`, read once per run.
+static FIXTURES: Lazy<Vec<Fixture>> = Lazy::new(|| {
+    let Some(paths) = env::var_os(SIMULATED_CAMERA_VAR) else {
+        return Vec::new();
+    };
+
+    env::split_paths(&paths)
+        .filter_map(|path| match Fixture::load(&path) {
+            Ok(fixture) => {
+                println!("Loaded simulated camera from {}", path.display());
+                Some(fixture)
+            }
+            Err(e) => {
+                eprintln!("Failed to load simulated camera {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+});
+
+/// A simulated camera: how it shows up on USB, what it reports about itself
+/// and which files its card holds.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Fixture {
+    vendor_id: u16,
+    product_id: u16,
+    product_name: Option<String>,
+    /// USB serial number. Leave it out to test the PTP and volume ids
+    serial: Option<String>,
+    /// What the backend reports once connected
+    identity: DeviceIdentity,
+    /// Directory whose files are on the card, relative to the manifest
+    root: Option<PathBuf>,
+    /// Files on the card besides those under `root`. Their content is
+    /// generated, so fixtures can list large videos without storing them
+    files: Vec<FixtureFile>,
+    /// Operations that fail, such as `{"detect": "claimed"}`
+    errors: HashMap<Operation, InjectedError>,
+    /// Files deleted during this run. The fixture itself is left untouched
+    #[serde(skip)]
+    deleted: Mutex<HashSet<PathBuf>>,
+}
+
+impl Default for Fixture {
+    /// A RICOH THETA Z1
+    fn default() -> Self {
+        Self {
+            vendor_id: 1482,
+            product_id: 877,
+            product_name: Some("RICOH THETA Z1".to_string()),
+            serial: Some("SIMULATED".to_string()),
+            identity: DeviceIdentity::default(),
+            root: None,
+            files: Vec::new(),
+            errors: HashMap::new(),
+            deleted: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureFile {
+    /// Path on the card, such as `DCIM/100RICOH/R0010001.JPG`
+    path: PathBuf,
+    size: u64,
+    #[serde(default)]
+    mtime: Option<u64>,
+    #[serde(default)]
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+enum Operation {
+    Detect,
+    List,
+    Stat,
+    Read,
+    Delete,
+}
+
+/// A failure the simulated camera reports instead of doing an operation.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+enum InjectedError {
+    /// Claimed by another app, such as the OS photo importer
+    Claimed,
+    /// Unplugged halfway
+    Unplugged,
+    /// Fails with this message
+    Failed(String),
+}
+
+impl From<&InjectedError> for AppError {
+    fn from(error: &InjectedError) -> Self {
+        match error {
+            InjectedError::Claimed => AppError::CameraUnavailable,
+            InjectedError::Unplugged => AppError::CameraNotFound,
+            InjectedError::Failed(message) => AppError::CameraOperation(message.clone()),
+        }
+    }
+}
+
+impl Fixture {
+    fn load(path: &Path) -> Result<Self, AppError> {
+        if path.is_dir() {
+            return Ok(Self {
+                root: Some(path.to_path_buf()),
+                ..Self::default()
+            });
+        }
+
+        let mut fixture: Fixture = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if let Some(root) = &fixture.root {
+            let manifest_dir = path.parent().unwrap_or(Path::new(""));
+            fixture.root = Some(manifest_dir.join(root));
+        }
+        Ok(fixture)
+    }
+
+    fn usb_device(&self, address: u8) -> UsbDevice {
+        UsbDevice {
+            bus: SIMULATED_BUS,
+            address,
+            ports: vec![address],
+            vendor_id: self.vendor_id,
+            product_id: self.product_id,
+            serial: self.serial.clone(),
+            product: self.product_name.clone(),
+        }
+    }
+
+    /// Fails if the fixture injects an error into `operation`.
+    fn check(&self, operation: Operation) -> Result<(), AppError> {
+        match self.errors.get(&operation) {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// The card under `root`, read like a mounted drive.
+    fn tree(&self) -> Option<MassStorageBackend> {
+        self.root.clone().map(MassStorageBackend::new)
+    }
+
+    fn listed(&self, file: &Path) -> Option<&FixtureFile> {
+        self.files.iter().find(|listed| listed.path == file)
+    }
+
+    fn is_deleted(&self, file: &Path) -> bool {
+        self.deleted.lock().unwrap().contains(file)
+    }
+}
+
+/// Whether `SIMULATED_CAMERA_VAR` is set. USB is then left alone, even if
+/// no fixture could be loaded, so tests never reach a real camera.
+pub fn is_enabled() -> bool {
+    env::var_os(SIMULATED_CAMERA_VAR).is_some()
+}
+
+/// Every simulated camera, as `usb::connected_cameras` returns them.
+pub fn cameras() -> Vec<(UsbDevice, &'static CameraInfo)> {
+    FIXTURES
+        .iter()
+        .zip(1..)
+        .filter_map(|(fixture, address)| {
+            let usb = fixture.usb_device(address);
+            let info = CAMERAS.get(usb.vendor_id, usb.product_id, usb.product.as_deref());
+            if info.is_none() {
+                eprintln!("Simulated camera {} is not a supported camera", usb.id());
+            }
+            Some((usb, info?))
+        })
+        .collect()
+}
+
+/// Serves the files of a fixture, so the whole detect, list and upload flow
+/// can run without a camera. Selected instead of the other backends when
+/// `SIMULATED_CAMERA_VAR` is set.
+#[derive(Debug)]
+pub struct SimulatedBackend {
+    fixture: &'static Fixture,
+}
+
+impl CameraBackend for SimulatedBackend {
+    fn detect(device: &UsbDevice) -> Result<Self, AppError> {
+        let fixture = FIXTURES
+            .get(usize::from(device.address).wrapping_sub(1))
+            .filter(|_| device.bus == SIMULATED_BUS)
+            .ok_or(AppError::CameraNotFound)?;
+        fixture.check(Operation::Detect)?;
+
+        println!("Connected to simulated camera {}", device.id());
+        Ok(Self { fixture })
+    }
+
+    fn name(&self) -> &'static str {
+        "simulated"
+    }
+
+    fn identity(&self) -> DeviceIdentity {
+        self.fixture.identity.clone()
+    }
+
+    fn list(&self) -> Result<Vec<FileInfo>, AppError> {
+        self.fixture.check(Operation::List)?;
+
+        let mut files: Vec<FileInfo> = match self.fixture.tree() {
+            Some(tree) => tree.list()?,
+            None => Vec::new(),
+        };
+        files.extend(self.fixture.files.iter().map(|file| {
+            FileInfo::new(
+                file.path.clone(),
+                file.size,
+                file.content_type.clone(),
+                file.mtime,
+            )
+        }));
+        files.retain(|file| !self.fixture.is_deleted(&file.path));
+
+        Ok(files)
+    }
+
+    fn stat(&self, file: &Path) -> Result<FileInfo, AppError> {
+        self.fixture.check(Operation::Stat)?;
+        self.find(file)?;
+
+        match self.fixture.listed(file) {
+            Some(listed) => Ok(FileInfo::new(
+                listed.path.clone(),
+                listed.size,
+                listed.content_type.clone(),
+                listed.mtime,
+            )),
+            None => self.tree(file)?.stat(file),
+        }
+    }
+
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+        self.fixture.check(Operation::Read)?;
+        self.find(file)?;
+
+        match self.fixture.listed(file) {
+            Some(listed) => {
+                let end = offset.saturating_add(len).min(listed.size);
+                Ok(generated(&listed.path, offset.min(end)..end))
+            }
+            None => self.tree(file)?.read(file, offset, len),
+        }
+    }
+
+    fn delete(&self, file: &Path) -> Result<(), AppError> {
+        self.fixture.check(Operation::Delete)?;
+        self.find(file)?;
+
+        self.fixture
+            .deleted
+            .lock()
+            .unwrap()
+            .insert(file.to_path_buf());
+        Ok(())
+    }
+}
+
+impl SimulatedBackend {
+    /// Fails like a camera would for a file that is not on the card.
+    fn find(&self, file: &Path) -> Result<(), AppError> {
+        let on_card = self.fixture.listed(file).is_some()
+            || self
+                .fixture
+                .root
+                .as_ref()
+                .is_some_and(|root| root.join(file).is_file());
+
+        if !on_card || self.fixture.is_deleted(file) {
+            return Err(AppError::CameraOperation(format!(
+                "{} not found on the camera",
+                file.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn tree(&self, file: &Path) -> Result<MassStorageBackend, AppError> {
+        self.fixture.tree().ok_or_else(|| {
+            AppError::CameraOperation(format!("{} not found on the camera", file.display()))
+        })
+    }
+}
+
+/// Bytes in `range` of a generated file. They differ from file to file so
+/// their checksums do too, and are the same on every read.
+fn generated(path: &Path, range: Range<u64>) -> Vec<u8> {
+    let seed = path.to_string_lossy().bytes().fold(0u64, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(u64::from(byte))
+    });
+    range
+        .map(|position| (seed.wrapping_add(position) % 251) as u8)
+        .collect()
+}
+
+#[cfg(test)]
+impl SimulatedBackend {
+    /// A simulated camera whose card holds generated files of these sizes.
+    pub fn with_files(files: &[(&str, u64)]) -> Self {
+        let fixture = Fixture {
+            files: files
+                .iter()
+                .map(|(path, size)| FixtureFile {
+                    path: PathBuf::from(path),
+                    size: *size,
+                    mtime: None,
+                    content_type: None,
+                })
+                .collect(),
+            ..Fixture::default()
+        };
+        Self {
+            fixture: Box::leak(Box::new(fixture)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO: &str = "DCIM/100RICOH/R0010002.MP4";
+
+    fn backend() -> SimulatedBackend {
+        SimulatedBackend::with_files(&[("DCIM/100RICOH/R0010001.JPG", 100), (VIDEO, 1000)])
+    }
+
+    #[test]
+    fn lists_and_stats_generated_files() {
+        let backend = backend();
+
+        let files = backend.list().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(backend.stat(Path::new(VIDEO)).unwrap().size, 1000);
+        assert!(backend.stat(Path::new("DCIM/missing.JPG")).is_err());
+    }
+
+    #[test]
+    fn reads_are_stable_and_stop_at_the_end() {
+        let backend = backend();
+        let video = Path::new(VIDEO);
+
+        let whole = backend.read(video, 0, 1000).unwrap();
+        assert_eq!(backend.read(video, 100, 50).unwrap(), whole[100..150]);
+        assert_eq!(backend.read(video, 990, 50).unwrap().len(), 10);
+        assert!(backend.read(video, 2000, 50).unwrap().is_empty());
+    }
+
+    #[test]
+    fn deleted_files_are_gone() {
+        let backend = backend();
+        let video = Path::new(VIDEO);
+
+        backend.delete(video).unwrap();
+        assert_eq!(backend.list().unwrap().len(), 1);
+        assert!(backend.read(video, 0, 10).is_err());
+        assert!(backend.delete(video).is_err());
+    }
+
+    #[test]
+    fn injects_errors_from_the_manifest() {
+        let fixture: Fixture =
+            serde_json::from_str(r#"{"errors": {"list": "claimed", "read": {"failed": "I/O"}}}"#)
+                .unwrap();
+        let backend = SimulatedBackend {
+            fixture: Box::leak(Box::new(fixture)),
+        };
+
+        assert!(matches!(backend.list(), Err(AppError::CameraUnavailable)));
+        assert!(matches!(
+            backend.read(Path::new(VIDEO), 0, 10),
+            Err(AppError::CameraOperation(message)) if message == "I/O"
+        ));
+    }
+
+    #[test]
+    fn generated_files_differ() {
+        let range = 0..64;
+        assert_ne!(
+            generated(Path::new("A.JPG"), range.clone()),
+            generated(Path::new("B.JPG"), range)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::camera::backend::CameraBackend;
+use crate::error::AppError;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Reads a camera file front to back in chunks of at most `chunk_size`
+/// bytes, so a multi-gigabyte video never has to fit in memory at once.
+pub struct FileStream {
+    backend: Arc<dyn CameraBackend>,
+    file: PathBuf,
+    size: u64,
+    offset: u64,
+    chunk_size: u64,
+}
+
+impl FileStream {
+    pub fn new(backend: Arc<dyn CameraBackend>, file: PathBuf, size: u64, chunk_size: u64) -> Self {
+        Self {
+            backend,
+            file,
+            size,
+            offset: 0,
+            chunk_size: chunk_size.max(1),
+        }
+    }
+
+    /// Streams the whole file into `writer`. Returns the bytes written.
+    pub fn copy_to(self, writer: &mut impl Write) -> Result<u64, AppError> {
+        let mut written = 0;
+        for chunk in self {
+            let chunk = chunk?;
+            writer.write_all(&chunk)?;
+            written += chunk.len() as u64;
+        }
+        Ok(written)
+    }
+}
+
+impl Iterator for FileStream {
+    type Item = Result<Vec<u8>, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.size {
+            return None;
+        }
+
+        let len = self.chunk_size.min(self.size - self.offset);
+        let chunk = self.backend.read(&self.file, self.offset, len);
+        match &chunk {
+            Ok(bytes) if bytes.is_empty() => {
+                let error = AppError::CameraOperation(format!(
+                    "{} ended after {} of {} bytes",
+                    self.file.display(),
+                    self.offset,
+                    self.size
+                ));
+                self.offset = self.size;
+                Some(Err(error))
+            }
+            Ok(bytes) => {
+                self.offset += bytes.len() as u64;
+                Some(chunk)
+            }
+            Err(_) => {
+                // Stop after the first error
+                self.offset = self.size;
+                Some(chunk)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::simulated::SimulatedBackend;
+    use std::path::Path;
+
+    const VIDEO: &str = "DCIM/R0010001.MP4";
+
+    fn backend() -> Arc<dyn CameraBackend> {
+        Arc::new(SimulatedBackend::with_files(&[(VIDEO, 1000)]))
+    }
+
+    #[test]
+    fn reads_in_chunks_of_at_most_chunk_size() {
+        let lens: Vec<usize> = FileStream::new(backend(), PathBuf::from(VIDEO), 1000, 300)
+            .map(|chunk| chunk.unwrap().len())
+            .collect();
+        assert_eq!(lens, vec![300, 300, 300, 100]);
+    }
+
+    #[test]
+    fn copies_the_whole_file() {
+        let backend = backend();
+        let mut copy = Vec::new();
+        let copied = FileStream::new(backend.clone(), PathBuf::from(VIDEO), 1000, 300)
+            .copy_to(&mut copy)
+            .unwrap();
+
+        assert_eq!(copied, 1000);
+        assert_eq!(copy, backend.read(Path::new(VIDEO), 0, 1000).unwrap());
+    }
+
+    #[test]
+    fn fails_when_the_file_ends_early() {
+        let mut copy = Vec::new();
+        let result = FileStream::new(backend(), PathBuf::from(VIDEO), 1500, 300).copy_to(&mut copy);
+
+        assert!(matches!(result, Err(AppError::CameraOperation(_))));
+        assert_eq!(copy.len(), 1000);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::camera::device_type::{CameraInfo, CAMERAS};
+use crate::camera::simulated;
+use once_cell::sync::Lazy;
+use rusb::{Device, DeviceDescriptor, UsbContext};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Bus, address, vendor id and product id of a plugged in device.
+type DeviceKey = (u8, u8, u16, u16);
+
+/// String descriptors read so far. Keeps a device's id stable while it stays
+/// plugged in, even if opening it fails later on, say once a backend has
+/// claimed it. Devices that could not be opened are not cached, so they are
+/// tried again.
+static DESCRIPTOR_STRINGS: Lazy<Mutex<HashMap<DeviceKey, DescriptorStrings>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Default)]
+struct DescriptorStrings {
+    serial: Option<String>,
+    product: Option<String>,
+}
+
+/// Where a camera is plugged in and how it identifies itself over USB.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsbDevice {
+    pub bus: u8,
+    /// Changes every time the device is plugged in
+    pub address: u8,
+    /// Hub ports from the root hub down, stable for a physical port
+    pub ports: Vec<u8>,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// Only readable when the OS lets us open the device
+    pub serial: Option<String>,
+    /// Product name the device reports, such as `RICOH THETA X`. Only
+    /// readable when the OS lets us open the device
+    pub product: Option<String>,
+}
+
+impl UsbDevice {
+    /// Reads the descriptors of `device`. Opens it to read the serial number
+    /// and product name, so this must not be called from a hotplug callback.
+    pub fn from_device<T: UsbContext>(device: &Device<T>) -> Option<Self> {
+        let desc = device.device_descriptor().ok()?;
+        let key = (
+            device.bus_number(),
+            device.address(),
+            desc.vendor_id(),
+            desc.product_id(),
+        );
+        let cached = DESCRIPTOR_STRINGS.lock().unwrap().get(&key).cloned();
+        let strings = match cached.or_else(|| read_strings(device, &desc)) {
+            Some(strings) => {
+                DESCRIPTOR_STRINGS
+                    .lock()
+                    .unwrap()
+                    .insert(key, strings.clone());
+                strings
+            }
+            None => DescriptorStrings::default(),
+        };
+
+        Some(Self {
+            bus: device.bus_number(),
+            address: device.address(),
+            ports: device.port_numbers().unwrap_or_default(),
+            vendor_id: desc.vendor_id(),
+            product_id: desc.product_id(),
+            serial: strings.serial,
+            product: strings.product,
+        })
+    }
+
+    /// Stable id of the device. The serial number makes it follow the camera
+    /// from port to port; without one, the id is tied to the port it is
+    /// plugged into.
+    pub fn id(&self) -> String {
+        match &self.serial {
+            Some(serial) => format!("{:04x}:{:04x}:{}", self.vendor_id, self.product_id, serial),
+            None => format!(
+                "{:04x}:{:04x}@{}-{}",
+                self.vendor_id,
+                self.product_id,
+                self.bus,
+                self.port_path()
+            ),
+        }
+    }
+
+    /// Hub ports joined by dots, as in `2.3`, the form Linux uses in sysfs.
+    pub fn port_path(&self) -> String {
+        self.ports
+            .iter()
+            .map(|port| port.to_string())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Port libgphoto2 and the gphoto2 CLI address this device by.
+    pub fn gphoto2_port(&self) -> String {
+        format!("usb:{:03},{:03}", self.bus, self.address)
+    }
+}
+
+/// Reads the serial number and product name. `None` if the device cannot be
+/// opened, as happens for a moment after it is plugged in.
+fn read_strings<T: UsbContext>(
+    device: &Device<T>,
+    desc: &DeviceDescriptor,
+) -> Option<DescriptorStrings> {
+    let handle = device.open().ok()?;
+    let clean = |value: rusb::Result<String>| {
+        value
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    Some(DescriptorStrings {
+        serial: clean(handle.read_serial_number_string_ascii(desc)),
+        product: clean(handle.read_product_string_ascii(desc)),
+    })
+}
+
+/// Every supported camera currently plugged in, or the simulated ones if
+/// `simulated::SIMULATED_CAMERA_VAR` is set.
+pub fn connected_cameras() -> Vec<(UsbDevice, &'static CameraInfo)> {
+    if simulated::is_enabled() {
+        return simulated::cameras();
+    }
+    match rusb::devices() {
+        Ok(devices) => devices
+            .iter()
+            .filter_map(|device| camera(&device))
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to enumerate USB devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// The device at `bus` and `address`, if one is plugged in there.
+pub fn device_at(bus: u8, address: u8) -> Option<UsbDevice> {
+    if simulated::is_enabled() {
+        return simulated::cameras()
+            .into_iter()
+            .map(|(usb, _)| usb)
+            .find(|usb| usb.bus == bus && usb.address == address);
+    }
+    let devices = rusb::devices().ok()?;
+    let device = devices
+        .iter()
+        .find(|device| device.bus_number() == bus && device.address() == address)?;
+    UsbDevice::from_device(&device)
+}
+
+fn camera<T: UsbContext>(device: &Device<T>) -> Option<(UsbDevice, &'static CameraInfo)> {
+    let desc = device.device_descriptor().ok()?;
+    if !CAMERAS.has_vendor(desc.vendor_id()) {
+        return None;
+    }
+    let usb = UsbDevice::from_device(device)?;
+    let info = CAMERAS.get(usb.vendor_id, usb.product_id, usb.product.as_deref())?;
+    Some((usb, info))
+}
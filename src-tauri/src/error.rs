@@ -19,26 +19,46 @@
 //! This prevents double-wrapping at the command boundary.
 
 use crate::ipc::pub_ipc_response::IpcStatus;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum AppError {
     // Authentication errors
-    #[error("OAuth configuration not found. Please run login first.")]
-    OAuthConfigNotFound,
-
     #[error("Authentication failed: {0}")]
     AuthenticationFailed(String),
 
     #[error("Device code expired. Please try again.")]
     DeviceCodeExpired,
 
+    #[error("Login was denied in the browser.")]
+    AccessDenied,
+
+    #[error("Login cancelled")]
+    LoginCancelled,
+
     #[error("Not authenticated. Please log in.")]
     NotAuthenticated,
 
+    /// No client id is configured and none is built in for the environment
+    #[error(
+        "Login is not configured: no client id is built in for {0}. Set one in the login settings."
+    )]
+    LoginNotConfigured(String),
+
+    /// The access token was rejected and has been refreshed, but the request
+    /// could not be replayed by itself
+    #[error("Access token refreshed. Please send the request again.")]
+    TokenRefreshed,
+
     // API errors
     #[error("API request failed: {status} - {message}")]
-    ApiRequest { status: u16, message: String },
+    ApiRequest {
+        status: u16,
+        message: String,
+        /// Delay requested by the server's `Retry-After` header, if any
+        retry_after: Option<Duration>,
+    },
 
     #[error("API not initialized. Please authenticate first.")]
     ApiNotInitialized,
@@ -81,6 +101,13 @@ pub enum AppError {
     #[error("Upload failed: {0}")]
     UploadFailed(String),
 
+    #[error("Upload cancelled")]
+    UploadCancelled,
+
+    /// The server stored different bytes than were sent
+    #[error("Checksum mismatch for {0}")]
+    ChecksumMismatch(String),
+
     // Network errors
     #[error("Network request failed: {0}")]
     Network(#[from] reqwest::Error),
@@ -116,9 +143,9 @@ impl AppError {
         match self {
             // Authentication/Authorization
             Self::NotAuthenticated
-            | Self::OAuthConfigNotFound
             | Self::AuthenticationFailed(_)
-            | Self::DeviceCodeExpired => IpcStatus::NotAuthenticated,
+            | Self::DeviceCodeExpired
+            | Self::AccessDenied => IpcStatus::NotAuthenticated,
 
             // API errors with status codes
             Self::ApiRequest { status, .. } => match *status {
@@ -136,21 +163,24 @@ impl AppError {
             Self::CacheNotFound(_) | Self::CameraNotFound => IpcStatus::NotFound,
 
             // Validation errors
-            Self::InvalidArgument(_) => IpcStatus::InvalidArgument,
+            Self::InvalidArgument(_) | Self::LoginNotConfigured(_) => IpcStatus::InvalidArgument,
 
             // Conflict errors
             Self::Conflict(_) => IpcStatus::Conflict,
 
+            // Cancelled by the user
+            Self::UploadCancelled | Self::LoginCancelled => IpcStatus::Cancelled,
+
             // Unavailable errors
-            Self::ApiNotInitialized | Self::CameraUnavailable | Self::NetworkTimeout => {
-                IpcStatus::Unavailable
-            }
+            Self::ApiNotInitialized
+            | Self::CameraUnavailable
+            | Self::NetworkTimeout
+            | Self::TokenRefreshed => IpcStatus::Unavailable,
 
-            // Network errors - check error type
+            // Network errors - timeouts and failures to reach or talk to the
+            // server are transient, anything else (e.g. decoding) is not
             Self::Network(e) => {
-                if e.is_timeout() {
-                    IpcStatus::Unavailable
-                } else if e.is_connect() {
+                if e.is_timeout() || e.is_connect() || e.is_request() {
                     IpcStatus::Unavailable
                 } else {
                     IpcStatus::InternalError
@@ -167,4 +197,26 @@ impl AppError {
             _ => IpcStatus::InternalError,
         }
     }
+
+    /// Whether retrying the failed operation may succeed.
+    ///
+    /// Transient failures (`IpcStatus::Unavailable`), request timeouts (408),
+    /// rate limiting (429) and server errors (5xx) are retryable. Other 4xx
+    /// responses, including authentication errors, are not.
+    ///
+    /// `TokenRefreshed` is retryable too: `upload_part` cannot replay a spent
+    /// body, so it relies on its callers retrying to send the part again.
+    /// So is `ChecksumMismatch`, as a part corrupted in transit is usually
+    /// stored intact when sent again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ChecksumMismatch(_) => true,
+            Self::ApiRequest { status, .. } => {
+                matches!(*status, 408 | 429) || (500..=599).contains(status)
+            }
+            // Nothing to wait for: the user has to log in first
+            Self::ApiNotInitialized => false,
+            _ => self.to_ipc_status() == IpcStatus::Unavailable,
+        }
+    }
 }
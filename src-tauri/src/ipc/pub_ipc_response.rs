@@ -16,6 +16,7 @@ pub enum IpcStatus {
     NotFound,
     ImATeapot,
     Unavailable,
+    Cancelled,
     InternalError,
 }
 
@@ -58,6 +59,7 @@ impl IpcStatus {
             IpcStatus::NotFound => "Resource not found",
             IpcStatus::ImATeapot => "🫖",
             IpcStatus::Unavailable => "Resource Unavailable",
+            IpcStatus::Cancelled => "Cancelled",
             IpcStatus::InternalError => "Internal Error. Please Contact OpenSpace",
         }
     }
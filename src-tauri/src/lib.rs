@@ -1,12 +1,21 @@
-use crate::api::oauth::auth::authenticate_user;
+use crate::api::oauth::auth::{self, authenticate_user};
 use crate::api::openspace::api::{get_user_info, make_request};
 use crate::api::openspace::pub_user_info::UserInfo;
 use crate::cache::file_cache::clear_skipped_files;
+use crate::cache::oauth_cache;
+use crate::cache::pub_oauth_config::OAuthConfig;
+use crate::cache::job_cache::clear_upload_jobs;
+use crate::cache::pub_settings_config::Settings;
+use crate::cache::settings_cache;
+use crate::cache::upload_cache::{clear_pending_uploads, clear_upload_history};
 use crate::cache::user_cache::{clear_user_config, get_user_config};
 use crate::error::AppError;
 use crate::ipc::pub_ipc_response::ToIpcResponse;
 use crate::traits::traits::ToJson;
+use crate::upload::queue;
+use crate::upload::staging::clear_staging;
 use serde_json::Value;
+use tauri::AppHandle;
 
 mod api;
 mod cache;
@@ -14,6 +23,8 @@ pub mod camera;
 mod error;
 mod ipc;
 mod traits;
+mod upload;
+mod util;
 
 fn err_response(app_error: AppError) -> Value {
     eprintln!("{}", app_error);
@@ -21,9 +32,10 @@ fn err_response(app_error: AppError) -> Value {
 }
 
 #[tauri::command]
-async fn get_user() -> Result<UserInfo, Value> {
+async fn get_user(app: AppHandle) -> Result<UserInfo, Value> {
+    let cancellation = auth::login_cancellation();
     if get_user_config().is_none() {
-        authenticate_user()
+        authenticate_user(&app, cancellation)
             .await
             .map_err(|e: AppError| e.to_ipc_response().to_json().unwrap())?;
     }
@@ -34,11 +46,30 @@ async fn get_user() -> Result<UserInfo, Value> {
         .ok_or_else(|| err_response(AppError::NotAuthenticated))
 }
 
+#[tauri::command]
+async fn cancel_login() {
+    auth::cancel_login()
+}
+
+#[tauri::command]
+async fn get_login_config() -> Result<OAuthConfig, Value> {
+    oauth_cache::get_oauth_config().map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn save_login_config(config: OAuthConfig) -> Result<(), Value> {
+    auth::save_login_config(&config).map_err(|e: AppError| err_response(e))
+}
+
 #[tauri::command]
 async fn clear_cache() -> Result<(), Value> {
     println!("Clearing cache");
     clear_user_config()
         .and_then(|_| clear_skipped_files())
+        .and_then(|_| clear_pending_uploads())
+        .and_then(|_| clear_upload_history())
+        .and_then(|_| clear_upload_jobs())
+        .and_then(|_| clear_staging())
         .map_err(|e: AppError| err_response(e))
 }
 
@@ -55,27 +86,131 @@ async fn req(
 }
 
 #[tauri::command]
-async fn get_camera() -> Result<Value, Value> {
-    camera::camera::find_camera()
+async fn get_cameras() -> Result<Value, Value> {
+    camera::camera::find_cameras()
         .to_json()
         .map_err(|e| err_response(AppError::from(e)))
 }
 
+// Omit device_id when only one camera is connected
+#[tauri::command]
+async fn get_camera(device_id: Option<String>) -> Result<Value, Value> {
+    camera::camera::select_camera(device_id.as_deref())
+        .and_then(|camera| Ok(camera.to_json()?))
+        .map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn get_camera_files(app: AppHandle, device_id: Option<String>) -> Result<Value, Value> {
+    let camera =
+        camera::camera::select_camera(device_id.as_deref()).map_err(|e: AppError| err_response(e))?;
+
+    if camera.files.is_empty() {
+        if let Some(access_error) = &camera.access_error {
+            return Err(err_response(AppError::CameraOperation(access_error.clone())));
+        }
+    }
+
+    queue::enqueue_camera_files(&app, &camera)
+        .and_then(|jobs| Ok(jobs.to_json()?))
+        .map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn pause_upload(job_id: Option<String>) -> Result<(), Value> {
+    upload::control::pause(job_id.as_deref()).map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn resume_upload(job_id: Option<String>) -> Result<(), Value> {
+    upload::control::resume(job_id.as_deref()).map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn cancel_upload(job_id: Option<String>) -> Result<(), Value> {
+    upload::control::cancel(job_id.as_deref()).map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn get_upload_history() -> Result<Value, Value> {
+    cache::upload_cache::get_upload_history()
+        .to_json()
+        .map_err(|e| err_response(AppError::from(e)))
+}
+
+#[tauri::command]
+async fn get_deletion_log() -> Result<Value, Value> {
+    cache::upload_cache::get_deletion_log()
+        .to_json()
+        .map_err(|e| err_response(AppError::from(e)))
+}
+
+#[tauri::command]
+async fn get_upload_jobs() -> Result<Value, Value> {
+    cache::job_cache::get_upload_jobs()
+        .and_then(|jobs| Ok(jobs.to_json()?))
+        .map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn reorder_upload_job(id: String, priority: i32) -> Result<Value, Value> {
+    queue::reorder_job(&id, priority)
+        .and_then(|job| Ok(job.to_json()?))
+        .map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn retry_upload_job(id: String) -> Result<Value, Value> {
+    queue::retry_job(&id)
+        .and_then(|job| Ok(job.to_json()?))
+        .map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn remove_upload_job(id: String) -> Result<(), Value> {
+    queue::remove_job(&id).map_err(|e: AppError| err_response(e))
+}
+
+#[tauri::command]
+async fn get_settings() -> Result<Settings, Value> {
+    Ok(settings_cache::get_settings())
+}
+
 #[tauri::command]
-async fn get_camera_files() -> Result<(), Value> {
-    Ok(())
+async fn save_settings(settings: Settings) -> Result<(), Value> {
+    settings_cache::save_settings(&settings).map_err(|e: AppError| err_response(e))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            queue::start(app.handle().clone());
+            camera::hotplug::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_user,
+            cancel_login,
+            get_login_config,
+            save_login_config,
             req,
+            get_cameras,
             get_camera,
             get_camera_files,
+            pause_upload,
+            resume_upload,
+            cancel_upload,
+            get_upload_history,
+            get_deletion_log,
+            get_upload_jobs,
+            reorder_upload_job,
+            retry_upload_job,
+            remove_upload_job,
             clear_cache,
+            get_settings,
+            save_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,152 @@
+use crate::error::AppError;
+use crate::upload::uploader::PART_SIZE;
+use crate::upload::source::UploadSource;
+use sha2::{Digest, Sha256};
+
+/// Bytes read from each end of a file for its partial hash.
+const PARTIAL_HASH_WINDOW: u64 = 1024 * 1024;
+
+/// Hex-encoded SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Composite checksum of a multipart upload: the SHA-256 of the concatenated
+/// raw part digests, suffixed with the number of parts (`<hex>-<n>`). It is
+/// not the SHA-256 of the file itself, and depends on `PART_SIZE`.
+///
+/// It is derived from the part checksums alone, so it can be computed while
+/// parts are read off the camera in any order, and for resumed uploads without
+/// reading the parts that were sent in an earlier session.
+pub fn composite_checksum<'a>(
+    part_checksums: impl IntoIterator<Item = &'a String>,
+) -> Result<String, AppError> {
+    let mut hasher = Sha256::new();
+    let mut count = 0;
+    for checksum in part_checksums {
+        let digest = hex::decode(checksum)
+            .map_err(|e| AppError::Internal(format!("Invalid part checksum: {}", e)))?;
+        hasher.update(digest);
+        count += 1;
+    }
+
+    Ok(format!("{}-{}", hex::encode(hasher.finalize()), count))
+}
+
+/// Cheap content fingerprint: SHA-256 of the size, the first and the last
+/// megabyte of the file. Blocking.
+pub fn partial_hash(source: &UploadSource) -> Result<String, AppError> {
+    let size = source.size;
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+
+    let head_len = size.min(PARTIAL_HASH_WINDOW);
+    hasher.update(source.read(0, head_len)?);
+
+    let tail_start = size.saturating_sub(PARTIAL_HASH_WINDOW).max(head_len);
+    if tail_start < size {
+        hasher.update(source.read(tail_start, size - tail_start)?);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Full content hash of a file. Equal to the `composite_checksum` an upload of
+/// the same file produces, so finished uploads can record it for free.
+/// Blocking; reads the whole file.
+pub fn content_hash(source: &UploadSource) -> Result<String, AppError> {
+    let mut part_checksums = Vec::new();
+    let mut offset = 0;
+
+    // Hash part by part, exactly as they are uploaded; an empty file is one empty part
+    loop {
+        let len = PART_SIZE.min(source.size - offset);
+        part_checksums.push(sha256_hex(&source.read(offset, len)?));
+        offset += len;
+        if offset == source.size {
+            break;
+        }
+    }
+
+    composite_checksum(&part_checksums)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::simulated::SimulatedBackend;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn source(size: u64) -> UploadSource {
+        let backend = SimulatedBackend::with_files(&[("DCIM/R0010001.MP4", size)]);
+        UploadSource::from_backend(Arc::new(backend), PathBuf::from("DCIM/R0010001.MP4"), size)
+    }
+
+    #[test]
+    fn composite_checksum_hashes_the_part_digests() {
+        let parts = [sha256_hex(b"first"), sha256_hex(b"second")];
+
+        let mut digests = Sha256::digest(b"first").to_vec();
+        digests.extend(Sha256::digest(b"second"));
+        assert_eq!(
+            composite_checksum(&parts).unwrap(),
+            format!("{}-2", sha256_hex(&digests))
+        );
+    }
+
+    #[test]
+    fn composite_checksum_depends_on_part_order() {
+        let parts = [sha256_hex(b"first"), sha256_hex(b"second")];
+        let reversed = [parts[1].clone(), parts[0].clone()];
+        assert_ne!(
+            composite_checksum(&parts).unwrap(),
+            composite_checksum(&reversed).unwrap()
+        );
+    }
+
+    #[test]
+    fn composite_checksum_rejects_invalid_parts() {
+        assert!(composite_checksum(&["not hex".to_string()]).is_err());
+    }
+
+    #[test]
+    fn content_hash_matches_the_uploaded_parts() {
+        let size = 2 * PART_SIZE + 10;
+        let source = source(size);
+
+        let parts: Vec<String> = [(0, PART_SIZE), (PART_SIZE, PART_SIZE), (2 * PART_SIZE, 10)]
+            .iter()
+            .map(|(offset, len)| sha256_hex(&source.read(*offset, *len).unwrap()))
+            .collect();
+        assert_eq!(
+            content_hash(&source).unwrap(),
+            composite_checksum(&parts).unwrap()
+        );
+    }
+
+    #[test]
+    fn content_hash_of_an_empty_file_is_one_empty_part() {
+        assert_eq!(
+            content_hash(&source(0)).unwrap(),
+            composite_checksum(&[sha256_hex(b"")]).unwrap()
+        );
+    }
+
+    #[test]
+    fn partial_hash_covers_size_and_both_ends() {
+        let small = source(100);
+        let mut hasher = Sha256::new();
+        hasher.update(100u64.to_le_bytes());
+        hasher.update(small.read(0, 100).unwrap());
+        assert_eq!(
+            partial_hash(&small).unwrap(),
+            hex::encode(hasher.finalize())
+        );
+
+        assert_ne!(
+            partial_hash(&source(3 * PARTIAL_HASH_WINDOW)).unwrap(),
+            partial_hash(&source(3 * PARTIAL_HASH_WINDOW + 1)).unwrap()
+        );
+    }
+}
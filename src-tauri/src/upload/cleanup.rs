@@ -0,0 +1,56 @@
+use crate::cache::pkg_upload_config::{DeletionRecord, UploadRecord};
+use crate::cache::pub_job_config::UploadJob;
+use crate::cache::settings_cache::get_settings;
+use crate::cache::upload_cache::add_deletion_record;
+use crate::camera::camera::backend_for;
+use crate::error::AppError;
+use crate::util::time::unix_now;
+use std::path::Path;
+
+/// Applies the delete-after-upload policy to a finalized upload. Blocking.
+///
+/// Files are only deleted once the server has confirmed their checksum.
+/// A failed deletion never fails the upload; the file stays on the camera.
+pub fn delete_after_upload(job: &UploadJob, record: &UploadRecord) {
+    let settings = get_settings();
+    if !settings.delete_after_upload {
+        return;
+    }
+    if !record.verified {
+        println!(
+            "Keeping {} on the camera: the server did not confirm its checksum",
+            job.filename
+        );
+        return;
+    }
+
+    let dry_run = settings.deletion_dry_run;
+    if dry_run {
+        println!(
+            "Dry run: would delete {} from {}",
+            job.filename, job.device_id
+        );
+    } else if let Err(e) = delete_from_camera(job) {
+        eprintln!("{}", e);
+        return;
+    } else {
+        println!("Deleted {} from {}", job.filename, job.device_id);
+    }
+
+    let deletion = DeletionRecord {
+        upload_id: record.upload_id.clone(),
+        device_id: record.device_id.clone(),
+        filename: record.filename.clone(),
+        size: record.size,
+        checksum: record.checksum.clone(),
+        dry_run,
+        deleted_at: unix_now(),
+    };
+    if let Err(e) = add_deletion_record(deletion) {
+        eprintln!("Failed to log deletion of {}: {}", job.filename, e);
+    }
+}
+
+fn delete_from_camera(job: &UploadJob) -> Result<(), AppError> {
+    backend_for(&job.device_id)?.delete(Path::new(&job.filename))
+}
@@ -0,0 +1,191 @@
+use crate::error::AppError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Requested state of a batch or of a single file in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// The batch currently being uploaded, if any. Pause, resume and cancel
+/// commands from the frontend act on this batch.
+static ACTIVE_BATCH: Mutex<Option<Arc<BatchControl>>> = Mutex::new(None);
+
+/// Pause/resume/cancel switches for one upload batch and each of its files.
+/// Files are keyed by job id, as cameras can have files of the same name.
+pub struct BatchControl {
+    state: watch::Sender<ControlState>,
+    files: Mutex<HashMap<String, watch::Sender<ControlState>>>,
+}
+
+impl BatchControl {
+    /// Creates a batch and makes it the active one.
+    pub fn start() -> Arc<Self> {
+        let batch = Arc::new(Self {
+            state: watch::Sender::new(ControlState::Running),
+            files: Mutex::new(HashMap::new()),
+        });
+        *ACTIVE_BATCH.lock().unwrap() = Some(batch.clone());
+        batch
+    }
+
+    /// Unregisters the batch once it has finished, unless a newer one replaced it.
+    pub fn finish(self: &Arc<Self>) {
+        let mut active = ACTIVE_BATCH.lock().unwrap();
+        if active
+            .as_ref()
+            .is_some_and(|batch| Arc::ptr_eq(batch, self))
+        {
+            *active = None;
+        }
+    }
+
+    pub fn state(&self) -> ControlState {
+        *self.state.borrow()
+    }
+
+    /// Registers the file of a job of this batch and returns its control
+    /// handle. A job that was cancelled earlier in the batch starts over as
+    /// running.
+    pub fn file(&self, job_id: &str) -> FileControl {
+        let mut files = self.files.lock().unwrap();
+        let sender = files
+            .entry(job_id.to_string())
+            .or_insert_with(|| watch::Sender::new(ControlState::Running));
+        if *sender.borrow() == ControlState::Cancelled {
+            *sender = watch::Sender::new(ControlState::Running);
+        }
+        let file = sender.subscribe();
+
+        FileControl {
+            batch: self.state.subscribe(),
+            file,
+        }
+    }
+
+    fn set_state(&self, job_id: Option<&str>, state: ControlState) -> Result<(), AppError> {
+        match job_id {
+            None => transition(&self.state, state),
+            Some(job_id) => {
+                let files = self.files.lock().unwrap();
+                let sender = files.get(job_id).ok_or_else(|| {
+                    AppError::InvalidArgument(format!(
+                        "Job {} is not part of the current upload",
+                        job_id
+                    ))
+                })?;
+                transition(sender, state);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Applies a state change. Cancellation is final and cannot be resumed.
+fn transition(sender: &watch::Sender<ControlState>, state: ControlState) {
+    sender.send_if_modified(|current| {
+        if *current == ControlState::Cancelled || *current == state {
+            return false;
+        }
+        *current = state;
+        true
+    });
+}
+
+fn with_active_batch(job_id: Option<&str>, state: ControlState) -> Result<(), AppError> {
+    let batch = ACTIVE_BATCH
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| AppError::Conflict("No upload in progress".to_string()))?;
+    batch.set_state(job_id, state)
+}
+
+/// Pauses the active batch, or the file of a single job in it. Parts already
+/// in flight finish; no new parts start until resumed.
+pub fn pause(job_id: Option<&str>) -> Result<(), AppError> {
+    with_active_batch(job_id, ControlState::Paused)
+}
+
+pub fn resume(job_id: Option<&str>) -> Result<(), AppError> {
+    with_active_batch(job_id, ControlState::Running)
+}
+
+/// Cancels the active batch, or the file of a single job in it. Parts in
+/// flight are aborted; parts that already finished stay recorded so the file
+/// can be resumed by a later upload.
+pub fn cancel(job_id: Option<&str>) -> Result<(), AppError> {
+    with_active_batch(job_id, ControlState::Cancelled)
+}
+
+/// A file's view of its own and its batch's control state.
+#[derive(Clone)]
+pub struct FileControl {
+    batch: watch::Receiver<ControlState>,
+    file: watch::Receiver<ControlState>,
+}
+
+impl FileControl {
+    /// Combined state: cancelling or pausing either the batch or the file applies.
+    pub fn state(&self) -> ControlState {
+        let (batch, file) = (*self.batch.borrow(), *self.file.borrow());
+        if batch == ControlState::Cancelled || file == ControlState::Cancelled {
+            ControlState::Cancelled
+        } else if batch == ControlState::Paused || file == ControlState::Paused {
+            ControlState::Paused
+        } else {
+            ControlState::Running
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state() == ControlState::Paused
+    }
+
+    /// Waits while paused. Fails with `UploadCancelled` once cancelled.
+    pub async fn checkpoint(&self) -> Result<(), AppError> {
+        let mut control = self.clone();
+        loop {
+            match control.state() {
+                ControlState::Running => return Ok(()),
+                ControlState::Cancelled => return Err(AppError::UploadCancelled),
+                ControlState::Paused => control.changed().await,
+            }
+        }
+    }
+
+    /// Runs `future` until it completes or the file is cancelled, whichever
+    /// comes first. Dropping the future aborts any request it has in flight.
+    pub async fn run<T>(
+        &self,
+        future: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        tokio::select! {
+            result = future => result,
+            _ = self.cancelled() => Err(AppError::UploadCancelled),
+        }
+    }
+
+    async fn cancelled(&self) {
+        let mut control = self.clone();
+        while control.state() != ControlState::Cancelled {
+            control.changed().await;
+        }
+    }
+
+    async fn changed(&mut self) {
+        let changed = tokio::select! {
+            result = self.batch.changed() => result,
+            result = self.file.changed() => result,
+        };
+        if changed.is_err() {
+            // The batch is gone, so the state can no longer change
+            std::future::pending::<()>().await;
+        }
+    }
+}
@@ -0,0 +1,10 @@
+pub mod checksum;
+pub mod cleanup;
+pub mod control;
+pub mod pool;
+pub mod progress;
+pub mod pub_upload_result;
+pub mod queue;
+pub mod source;
+pub mod staging;
+pub mod uploader;
@@ -0,0 +1,37 @@
+use crate::cache::pub_settings_config::Settings;
+use crate::error::AppError;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Bounds how much of a batch runs at once.
+///
+/// One pool is shared by every file of a batch, so `max_concurrent_parts`
+/// caps the parts in flight across the whole batch rather than per file.
+pub struct UploadPool {
+    files: Semaphore,
+    parts: Semaphore,
+}
+
+impl UploadPool {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            files: Semaphore::new(settings.max_concurrent_files.max(1)),
+            parts: Semaphore::new(settings.max_concurrent_parts.max(1)),
+        }
+    }
+
+    /// Waits for a free file slot. Held for the whole upload of one file.
+    pub async fn acquire_file(&self) -> Result<SemaphorePermit<'_>, AppError> {
+        self.files
+            .acquire()
+            .await
+            .map_err(|e| AppError::Internal(format!("Upload pool closed: {}", e)))
+    }
+
+    /// Waits for a free part slot. Held while a part is read and sent.
+    pub async fn acquire_part(&self) -> Result<SemaphorePermit<'_>, AppError> {
+        self.parts
+            .acquire()
+            .await
+            .map_err(|e| AppError::Internal(format!("Upload pool closed: {}", e)))
+    }
+}
@@ -0,0 +1,223 @@
+use crate::cache::pub_job_config::UploadJob;
+use crate::error::AppError;
+use crate::upload::pub_upload_result::UploadState;
+use bytes::Bytes;
+use reqwest::Body;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+// Event names the frontend listens for in `UploadProgressContext`
+pub const UPLOAD_PROGRESS_EVENT: &str = "upload-progress";
+pub const UPLOAD_COMPLETE_EVENT: &str = "upload-complete";
+pub const UPLOAD_SKIPPED_EVENT: &str = "upload-skipped";
+pub const UPLOAD_ERROR_EVENT: &str = "upload-error";
+
+/// Minimum time between two byte-progress events for the same file.
+/// State changes are always emitted immediately.
+const PROGRESS_THROTTLE: Duration = Duration::from_millis(250);
+
+/// Size of the slices a part body is streamed in, which bounds how often
+/// bytes are counted while a part is in flight.
+const PROGRESS_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Payload of `upload-progress`. Mirrors `UploadStatus` in `src/types/upload.ts`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadStatus {
+    pub job_id: String,
+    pub filename: String,
+    pub bytes_uploaded: u64,
+    pub total_bytes: u64,
+    pub percentage: f64,
+    pub status: UploadState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileEvent<'a> {
+    job_id: &'a str,
+    filename: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileErrorEvent<'a> {
+    job_id: &'a str,
+    filename: &'a str,
+    error: &'a str,
+}
+
+/// Reports the progress of one job's file to the frontend.
+pub struct ProgressReporter {
+    app: AppHandle,
+    job_id: String,
+    filename: String,
+    total_bytes: AtomicU64,
+    bytes_uploaded: AtomicU64,
+    state: Mutex<UploadState>,
+    last_emit: Mutex<Option<Instant>>,
+}
+
+impl ProgressReporter {
+    pub fn new(app: &AppHandle, job: &UploadJob) -> Arc<Self> {
+        Arc::new(Self {
+            app: app.clone(),
+            job_id: job.id.clone(),
+            filename: job.filename.clone(),
+            total_bytes: AtomicU64::new(job.size),
+            bytes_uploaded: AtomicU64::new(0),
+            state: Mutex::new(UploadState::Pending),
+            last_emit: Mutex::new(None),
+        })
+    }
+
+    pub fn set_total_bytes(&self, total_bytes: u64) {
+        self.total_bytes.store(total_bytes, Ordering::Relaxed);
+    }
+
+    /// Starts the counter at the bytes already on the server, e.g. when resuming.
+    pub fn set_bytes_uploaded(&self, bytes: u64) {
+        self.bytes_uploaded.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Moves the file to a new state and notifies the frontend.
+    pub fn set_state(&self, state: UploadState) {
+        *self.state.lock().unwrap() = state;
+        if state == UploadState::Completed {
+            self.bytes_uploaded
+                .store(self.total_bytes.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+
+        self.emit_status(None);
+        let event = match state {
+            UploadState::Completed => UPLOAD_COMPLETE_EVENT,
+            UploadState::Skipped => UPLOAD_SKIPPED_EVENT,
+            _ => return,
+        };
+        self.emit(
+            event,
+            FileEvent {
+                job_id: &self.job_id,
+                filename: &self.filename,
+            },
+        );
+    }
+
+    pub fn fail(&self, error: &AppError) {
+        let message = error.to_string();
+        *self.state.lock().unwrap() = UploadState::Failed;
+
+        self.emit_status(Some(message.clone()));
+        self.emit(
+            UPLOAD_ERROR_EVENT,
+            FileErrorEvent {
+                job_id: &self.job_id,
+                filename: &self.filename,
+                error: &message,
+            },
+        );
+    }
+
+    /// Counts bytes handed to the network. Emits at most once per throttle window.
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_uploaded.fetch_add(bytes, Ordering::Relaxed);
+
+        let mut last_emit = self.last_emit.lock().unwrap();
+        let now = Instant::now();
+        if last_emit.is_some_and(|last| now.duration_since(last) < PROGRESS_THROTTLE) {
+            return;
+        }
+        *last_emit = Some(now);
+        drop(last_emit);
+
+        self.emit_status(None);
+    }
+
+    /// Takes back bytes of a part attempt that did not go through.
+    pub fn remove_bytes(&self, bytes: u64) {
+        // Never underflows: only bytes previously added are removed
+        self.bytes_uploaded.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    fn emit_status(&self, error: Option<String>) {
+        let total_bytes = self.total_bytes.load(Ordering::Relaxed);
+        let bytes_uploaded = self.bytes_uploaded.load(Ordering::Relaxed).min(total_bytes);
+        let percentage = if total_bytes == 0 {
+            0.0
+        } else {
+            bytes_uploaded as f64 * 100.0 / total_bytes as f64
+        };
+
+        let status = UploadStatus {
+            job_id: self.job_id.clone(),
+            filename: self.filename.clone(),
+            bytes_uploaded,
+            total_bytes,
+            percentage,
+            status: *self.state.lock().unwrap(),
+            error,
+        };
+        self.emit(UPLOAD_PROGRESS_EVENT, status);
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Err(e) = self.app.emit(event, payload) {
+            eprintln!("Failed to emit {} for {}: {}", event, self.filename, e);
+        }
+    }
+}
+
+/// Counts the bytes of one attempt at sending a part.
+///
+/// If the attempt is dropped without being committed (it failed, will be
+/// retried, or was cancelled) its bytes are taken back off the file's
+/// progress, so retries never push the total past the file size.
+pub struct PartProgress {
+    reporter: Arc<ProgressReporter>,
+    sent: Arc<AtomicU64>,
+    committed: bool,
+}
+
+impl PartProgress {
+    pub fn new(reporter: &Arc<ProgressReporter>) -> Self {
+        Self {
+            reporter: reporter.clone(),
+            sent: Arc::new(AtomicU64::new(0)),
+            committed: false,
+        }
+    }
+
+    /// Wraps the part in a streaming body that reports bytes as reqwest reads them.
+    pub fn body(&self, bytes: Vec<u8>) -> Body {
+        let bytes = Bytes::from(bytes);
+        let len = bytes.len();
+        let (reporter, sent) = (self.reporter.clone(), self.sent.clone());
+        let chunks = (0..len).step_by(PROGRESS_CHUNK_SIZE).map(move |start| {
+            let chunk = bytes.slice(start..len.min(start + PROGRESS_CHUNK_SIZE));
+            sent.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            reporter.add_bytes(chunk.len() as u64);
+            Ok::<Bytes, std::io::Error>(chunk)
+        });
+
+        Body::wrap_stream(futures_util::stream::iter(chunks))
+    }
+
+    /// Keeps the counted bytes once the server has acknowledged the part.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for PartProgress {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.reporter
+                .remove_bytes(self.sent.load(Ordering::Relaxed));
+        }
+    }
+}
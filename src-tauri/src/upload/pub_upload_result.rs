@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle of a single file upload. Serialized to match `UploadStatus.status`
+/// in `src/types/upload.ts`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadState {
+    Pending,
+    Uploading,
+    Paused,
+    Completed,
+    Skipped,
+    Failed,
+    Cancelled,
+}
@@ -0,0 +1,311 @@
+use crate::api::http::retry::RetryPolicy;
+use crate::cache::job_cache::{
+    claim_next_upload_job, enqueue_upload_jobs, next_upload_job_due, remove_upload_job,
+    transition_upload_jobs, update_upload_job,
+};
+use crate::cache::pub_job_config::{JobState, UploadJob};
+use crate::cache::settings_cache::get_settings;
+use crate::camera::camera::CameraWithFiles;
+use crate::error::AppError;
+use crate::upload::control::{BatchControl, ControlState, FileControl};
+use crate::upload::pool::UploadPool;
+use crate::upload::progress::ProgressReporter;
+use crate::upload::pub_upload_result::UploadState;
+use crate::upload::staging::remove_staged;
+use crate::upload::uploader::upload_file;
+use crate::util::time::unix_now;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+
+/// Times the worker starts a job before a retryable failure becomes final.
+/// Each start already retries its requests with backoff.
+const MAX_JOB_ATTEMPTS: u32 = 3;
+
+/// Wait before a job that failed with a retryable error starts again.
+const JOB_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: MAX_JOB_ATTEMPTS,
+    base_delay: Duration::from_secs(30),
+    max_delay: Duration::from_secs(10 * 60),
+};
+
+/// Wakes the worker when jobs are queued while it is idle.
+static QUEUE_WAKER: Notify = Notify::const_new();
+
+/// Restores jobs interrupted by the last shutdown and starts the worker that
+/// drains the queue in the background.
+pub fn start(app: AppHandle) {
+    match transition_upload_jobs(JobState::Running, JobState::Queued, unix_now()) {
+        Ok(0) => {}
+        Ok(restored) => println!("Restored {} interrupted upload jobs", restored),
+        Err(e) => eprintln!("Failed to restore upload jobs: {}", e),
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            drain(&app).await;
+            QUEUE_WAKER.notified().await;
+        }
+    });
+}
+
+fn wake() {
+    QUEUE_WAKER.notify_one();
+}
+
+/// Queues every file found on the camera and returns their jobs.
+pub fn enqueue_camera_files(
+    app: &AppHandle,
+    camera: &CameraWithFiles,
+) -> Result<Vec<UploadJob>, AppError> {
+    let device_id = camera.device_id.clone();
+    let now = unix_now();
+
+    let jobs: Vec<UploadJob> = camera
+        .files
+        .iter()
+        .map(|file| {
+            UploadJob::new(
+                new_job_id(),
+                device_id.clone(),
+                file.path.display().to_string(),
+                file.size,
+                now,
+            )
+        })
+        .collect();
+
+    let jobs = enqueue_upload_jobs(jobs)?;
+    println!("Queued {} files from {}", jobs.len(), device_id);
+
+    // Announce the new jobs up front so the UI can list them as pending
+    for job in jobs.iter().filter(|job| job.state == JobState::Queued) {
+        ProgressReporter::new(app, job).set_state(UploadState::Pending);
+    }
+    wake();
+
+    Ok(jobs)
+}
+
+/// Queues a failed or finished job again.
+pub fn retry_job(id: &str) -> Result<UploadJob, AppError> {
+    let job = update_upload_job(id, |job| {
+        if job.state.is_active() {
+            return Err(AppError::Conflict(format!(
+                "{} is already queued",
+                job.filename
+            )));
+        }
+        job.state = JobState::Queued;
+        job.attempts = 0;
+        job.last_error = None;
+        job.not_before = None;
+        job.updated_at = unix_now();
+        Ok(())
+    })?;
+    wake();
+    Ok(job)
+}
+
+/// Changes where a job sits in the queue. Takes effect the next time the
+/// worker picks a job.
+pub fn reorder_job(id: &str, priority: i32) -> Result<UploadJob, AppError> {
+    update_upload_job(id, |job| {
+        job.priority = priority;
+        job.updated_at = unix_now();
+        Ok(())
+    })
+}
+
+pub fn remove_job(id: &str) -> Result<(), AppError> {
+    remove_upload_job(id)
+}
+
+/// Uploads queued jobs until none are left, waiting for those queued again
+/// after a failure to be due.
+///
+/// All jobs run by one drain share a batch, so pause, resume and cancel act
+/// on everything in flight. Cancelling the batch also cancels the jobs still
+/// waiting in the queue.
+async fn drain(app: &AppHandle) {
+    let settings = get_settings();
+    let pool = Arc::new(UploadPool::new(&settings));
+    let batch = BatchControl::start();
+    let mut running = JoinSet::new();
+    let mut cancelled = false;
+
+    loop {
+        if !cancelled && batch.state() == ControlState::Cancelled {
+            cancelled = true;
+            match transition_upload_jobs(JobState::Queued, JobState::Cancelled, unix_now()) {
+                Ok(count) => println!("Cancelled {} queued upload jobs", count),
+                Err(e) => eprintln!("Failed to cancel queued upload jobs: {}", e),
+            }
+        }
+
+        while !cancelled && running.len() < settings.max_concurrent_files {
+            match claim_next_upload_job(unix_now()) {
+                Ok(Some(job)) => {
+                    let control = batch.file(&job.id);
+                    running.spawn(run_job(app.clone(), job, pool.clone(), control));
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("Failed to pick the next upload job: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let next_due = if cancelled {
+            None
+        } else {
+            next_upload_job_due().unwrap_or_else(|e| {
+                eprintln!("Failed to read upload jobs: {}", e);
+                None
+            })
+        };
+        if running.is_empty() && next_due.is_none() {
+            break;
+        }
+        if cancelled {
+            // Leave wake-ups for the next drain, which gets a fresh batch
+            running.join_next().await;
+        } else {
+            let until_due = async {
+                match next_due {
+                    Some(due) => {
+                        let wait = due.saturating_sub(unix_now());
+                        tokio::time::sleep(Duration::from_secs(wait)).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                Some(_) = running.join_next() => {}
+                _ = QUEUE_WAKER.notified() => {}
+                _ = until_due => {}
+            }
+        }
+    }
+
+    batch.finish();
+}
+
+/// Uploads one job and records how it ended.
+async fn run_job(app: AppHandle, job: UploadJob, pool: Arc<UploadPool>, control: FileControl) {
+    let filename = &job.filename;
+    let reporter = ProgressReporter::new(&app, &job);
+
+    let (state, error, not_before) = match upload_file(&job, &reporter, &pool, &control).await {
+        Ok(status) => {
+            reporter.set_state(status);
+            match status {
+                UploadState::Skipped => (JobState::Skipped, None, None),
+                _ => (JobState::Completed, None, None),
+            }
+        }
+        Err(AppError::UploadCancelled) => {
+            reporter.set_state(UploadState::Cancelled);
+            (JobState::Cancelled, None, None)
+        }
+        Err(e) if e.is_retryable() && job.attempts < MAX_JOB_ATTEMPTS => {
+            let now = unix_now();
+            let retry_at = retry_at(&e, job.attempts, now);
+            eprintln!(
+                "Failed to upload {} (attempt {}/{}): {}. Queued again in {}s",
+                filename,
+                job.attempts,
+                MAX_JOB_ATTEMPTS,
+                e,
+                retry_at - now
+            );
+            reporter.set_state(UploadState::Pending);
+            (JobState::Queued, Some(e.to_string()), Some(retry_at))
+        }
+        Err(e) => {
+            eprintln!("Failed to upload {}: {}", filename, e);
+            reporter.fail(&e);
+            (JobState::Failed, Some(e.to_string()), None)
+        }
+    };
+
+    // A job that will run again keeps its staged copy
+    if state != JobState::Queued {
+        if let Err(e) = remove_staged(&job) {
+            eprintln!("Failed to remove staged copy of {}: {}", filename, e);
+        }
+    }
+
+    let saved = update_upload_job(&job.id, |job| {
+        job.state = state;
+        job.last_error = error;
+        job.not_before = not_before;
+        job.updated_at = unix_now();
+        Ok(())
+    });
+    if let Err(e) = saved {
+        eprintln!("Failed to save upload job for {}: {}", filename, e);
+    }
+}
+
+/// When a job that failed with `error` on its `attempts`th start may start
+/// again, in seconds since the Unix epoch.
+fn retry_at(error: &AppError, attempts: u32, now: u64) -> u64 {
+    let delay = JOB_RETRY_POLICY.delay_for(attempts, error);
+    now + delay.as_secs_f64().ceil() as u64
+}
+
+fn new_job_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> AppError {
+        AppError::ApiRequest {
+            status,
+            message: String::new(),
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn retries_wait_for_retry_after() {
+        let error = api_error(429, Some(Duration::from_secs(90)));
+        assert_eq!(retry_at(&error, 1, 1_000), 1_090);
+    }
+
+    #[test]
+    fn retries_back_off() {
+        let error = api_error(503, None);
+        let first = retry_at(&error, 1, 1_000);
+        assert!((1_015..=1_030).contains(&first));
+
+        let capped = retry_at(&error, 10, 1_000);
+        assert!((1_300..=1_600).contains(&capped));
+    }
+
+    #[test]
+    fn requeued_jobs_are_due_once_not_before_passes() {
+        let mut job = UploadJob::new(
+            "id".to_string(),
+            "cam".to_string(),
+            "R001.JPG".to_string(),
+            10,
+            0,
+        );
+        assert!(job.is_due(1_000));
+
+        job.not_before = Some(1_030);
+        assert!(!job.is_due(1_000));
+        assert!(job.is_due(1_030));
+
+        job.state = JobState::Failed;
+        assert!(!job.is_due(2_000));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::cache::pub_job_config::UploadJob;
+use crate::camera::backend::CameraBackend;
+use crate::camera::camera::backend_for;
+use crate::camera::mass_storage::MassStorageBackend;
+use crate::error::AppError;
+use crate::upload::staging::{stage, staged_name, staging_dir};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Where the bytes of one upload are read from: the file on its camera, or
+/// its copy in the staging directory, which is read like a mounted card.
+pub struct UploadSource {
+    backend: Arc<dyn CameraBackend>,
+    file: PathBuf,
+    pub size: u64,
+    pub content_type: String,
+}
+
+impl UploadSource {
+    /// Finds the job's file on its camera, copying it to the staging
+    /// directory first when `staged` is set or the backend cannot read part
+    /// of a file. Blocking.
+    pub fn open(job: &UploadJob, staged: bool) -> Result<Self, AppError> {
+        let backend = backend_for(&job.device_id)?;
+        let file = PathBuf::from(&job.filename);
+        let info = backend.stat(&file)?;
+        let (size, content_type) = (info.size, info.content_type);
+
+        if !staged && backend.supports_partial_reads() {
+            return Ok(Self {
+                backend,
+                file,
+                size,
+                content_type,
+            });
+        }
+
+        stage(job, backend, &file, size)?;
+        Ok(Self {
+            backend: Arc::new(MassStorageBackend::new(staging_dir())),
+            file: staged_name(job),
+            size,
+            content_type,
+        })
+    }
+
+    /// Reads `file` straight from `backend`.
+    #[cfg(test)]
+    pub fn from_backend(backend: Arc<dyn CameraBackend>, file: PathBuf, size: u64) -> Self {
+        Self {
+            backend,
+            file,
+            size,
+            content_type: "application/octet-stream".to_string(),
+        }
+    }
+
+    /// Reads exactly `len` bytes at `offset`. Blocking.
+    pub fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+        let bytes = self.backend.read(&self.file, offset, len)?;
+        if bytes.len() as u64 != len {
+            return Err(AppError::CameraOperation(format!(
+                "Read {} of {} bytes at offset {} of {}",
+                bytes.len(),
+                len,
+                offset,
+                self.file.display()
+            )));
+        }
+        Ok(bytes)
+    }
+}
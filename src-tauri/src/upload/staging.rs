@@ -0,0 +1,163 @@
+use crate::cache::pub_job_config::UploadJob;
+use crate::cache::root_cache::STORAGE_PATH;
+use crate::camera::backend::CameraBackend;
+use crate::camera::stream::FileStream;
+use crate::error::AppError;
+use crate::upload::uploader::PART_SIZE;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Directory in the cache where files are copied before they are uploaded.
+pub const STAGING_DIR: &str = "staging";
+
+pub fn staging_dir() -> PathBuf {
+    STORAGE_PATH.join(STAGING_DIR)
+}
+
+/// Name of a job's copy inside `staging_dir()`.
+pub fn staged_name(job: &UploadJob) -> PathBuf {
+    PathBuf::from(&job.id)
+}
+
+/// Copies a job's file off the camera into the staging directory. A complete
+/// copy left by an earlier attempt is reused. Blocking.
+pub fn stage(
+    job: &UploadJob,
+    backend: Arc<dyn CameraBackend>,
+    file: &Path,
+    size: u64,
+) -> Result<(), AppError> {
+    let path = staging_dir().join(staged_name(job));
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() == size) {
+        println!("Reusing staged copy of {}", job.filename);
+        return Ok(());
+    }
+
+    println!("Staging {} ({} bytes)", job.filename, size);
+    fs::create_dir_all(staging_dir())?;
+    copy_off_camera(backend, file, size, &path)
+}
+
+/// Copies `file` to `path`, in one fetch for backends without partial
+/// reads. Blocking.
+fn copy_off_camera(
+    backend: Arc<dyn CameraBackend>,
+    file: &Path,
+    size: u64,
+    path: &Path,
+) -> Result<(), AppError> {
+    // Copy under a temporary name so an interrupted copy is never reused
+    let tmp_path = path.with_extension("part");
+    let copied = if backend.supports_partial_reads() {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        let copied =
+            FileStream::new(backend, file.to_path_buf(), size, PART_SIZE).copy_to(&mut writer)?;
+        writer.flush()?;
+        copied
+    } else {
+        backend.download(file, &tmp_path)?
+    };
+    if copied != size {
+        return Err(AppError::CameraOperation(format!(
+            "Staged {} of {} bytes of {}",
+            copied,
+            size,
+            file.display()
+        )));
+    }
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Deletes a job's staged copy, if it has one.
+pub fn remove_staged(job: &UploadJob) -> Result<(), AppError> {
+    let path = staging_dir().join(staged_name(job));
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn clear_staging() -> Result<(), AppError> {
+    match fs::remove_dir_all(staging_dir()) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::pub_file_info::FileInfo;
+    use crate::camera::usb::UsbDevice;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A camera that, like the gphoto2 CLI, fetches the whole file on every
+    /// read, and counts its fetches.
+    #[derive(Debug, Default)]
+    struct WholeFileCamera {
+        fetches: AtomicUsize,
+    }
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    impl CameraBackend for WholeFileCamera {
+        fn detect(_device: &UsbDevice) -> Result<Self, AppError> {
+            Ok(Self::default())
+        }
+
+        fn name(&self) -> &'static str {
+            "whole-file"
+        }
+
+        fn list(&self) -> Result<Vec<FileInfo>, AppError> {
+            Ok(Vec::new())
+        }
+
+        fn stat(&self, file: &Path) -> Result<FileInfo, AppError> {
+            Ok(FileInfo::new(
+                file.to_path_buf(),
+                CONTENT.len() as u64,
+                None,
+                None,
+            ))
+        }
+
+        fn read(&self, _file: &Path, offset: u64, len: u64) -> Result<Vec<u8>, AppError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            let end = (offset + len).min(CONTENT.len() as u64);
+            Ok(CONTENT[offset as usize..end as usize].to_vec())
+        }
+
+        fn supports_partial_reads(&self) -> bool {
+            false
+        }
+
+        fn download(&self, _file: &Path, dest: &Path) -> Result<u64, AppError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            fs::write(dest, CONTENT)?;
+            Ok(CONTENT.len() as u64)
+        }
+
+        fn delete(&self, _file: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stages_whole_file_backends_in_one_fetch() {
+        let dir = std::env::temp_dir().join(format!("altoid-staging-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let camera = Arc::new(WholeFileCamera::default());
+        let path = dir.join("job");
+
+        copy_off_camera(camera.clone(), Path::new("R0010001.MP4"), 10, &path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+        assert_eq!(camera.fetches.load(Ordering::SeqCst), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,409 @@
+use crate::api::http::retry::{with_retry, RetryPolicy};
+use crate::api::openspace::api::{complete_upload, get_or_create_upload, upload_part};
+use crate::api::openspace::tictac::{CompleteUploadRequest, CompletedPart, TicTacUploadRequest};
+use crate::cache::file_cache::{add_skipped_file, is_file_skipped};
+use crate::cache::pkg_upload_config::{PendingUpload, UploadRecord};
+use crate::cache::pub_job_config::UploadJob;
+use crate::cache::settings_cache::get_settings;
+use crate::cache::upload_cache::{
+    add_upload_record, get_pending_upload, remove_pending_upload, save_pending_upload,
+};
+use crate::error::AppError;
+use crate::upload::checksum::{composite_checksum, content_hash, partial_hash, sha256_hex};
+use crate::upload::cleanup::delete_after_upload;
+use crate::upload::control::FileControl;
+use crate::upload::pool::UploadPool;
+use crate::upload::progress::{PartProgress, ProgressReporter};
+use crate::upload::pub_upload_result::UploadState;
+use crate::upload::source::UploadSource;
+use crate::util::time::unix_now;
+use futures_util::future::try_join_all;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::SemaphorePermit;
+
+/// Size of each part of a multipart upload. The last part may be smaller.
+pub const PART_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Times a part the server stored corrupted is sent again before the upload
+/// fails.
+const MAX_CHECKSUM_RETRIES: u32 = 2;
+
+/// Uploads the file of one queued job.
+///
+/// Parts are uploaded in parallel, bounded by the part slots of `pool`.
+/// Progress is streamed to the frontend through `reporter`.
+pub async fn upload_file(
+    job: &UploadJob,
+    reporter: &Arc<ProgressReporter>,
+    pool: &UploadPool,
+    control: &FileControl,
+) -> Result<UploadState, AppError> {
+    let _file_slot = acquire_while_running(control, reporter, UploadState::Pending, || {
+        pool.acquire_file()
+    })
+    .await?;
+
+    let device_id = job.device_id.as_str();
+    let filename = job.filename.clone();
+
+    // Staging copies the whole file off the camera before anything is read
+    let stage = get_settings().stage_files;
+    let source = {
+        let job = job.clone();
+        Arc::new(
+            control
+                .run(blocking(move || UploadSource::open(&job, stage)))
+                .await?,
+        )
+    };
+    let size = source.size;
+    reporter.set_total_bytes(size);
+
+    let (partial, skipped) = {
+        let (source, filename, device_id) = (source.clone(), filename.clone(), device_id.to_string());
+        control
+            .run(blocking(move || {
+                let partial = partial_hash(&source)?;
+                let skipped =
+                    is_file_skipped(&filename, size as i64, &device_id, &partial, || {
+                        content_hash(&source)
+                    })?;
+                Ok((partial, skipped))
+            }))
+            .await?
+    };
+    if skipped {
+        println!("Skipping {}: already uploaded", filename);
+        return Ok(UploadState::Skipped);
+    }
+
+    let num_parts = part_count(size);
+    let request = TicTacUploadRequest::new(
+        device_id.to_string(),
+        filename.clone(),
+        source.content_type.clone(),
+        size as i64,
+        num_parts,
+    );
+
+    // The server returns no upload id when it already has this file
+    let Some(upload_id) = control.run(get_or_create_upload(&request)).await?.upload_id else {
+        println!("Skipping {}: server already has it", filename);
+        if let Some(stale) = get_pending_upload(device_id, &filename, size as i64)? {
+            remove_pending_upload(&stale)?;
+        }
+        let source = source.clone();
+        let content = control
+            .run(blocking(move || content_hash(&source)))
+            .await?;
+        add_skipped_file(&filename, size as i64, device_id, &partial, &content)?;
+        return Ok(UploadState::Skipped);
+    };
+
+    let pending = resume_or_start(upload_id, device_id, &filename, size, num_parts)?;
+
+    println!(
+        "Uploading {} ({} bytes, {} parts)",
+        filename, size, num_parts
+    );
+    let resumed_bytes = (1..=num_parts)
+        .filter(|part_number| pending.is_part_completed(*part_number))
+        .map(|part_number| part_len(part_number, size))
+        .sum();
+    reporter.set_bytes_uploaded(resumed_bytes);
+    reporter.set_state(UploadState::Uploading);
+
+    let remaining_parts: Vec<i32> = (1..=num_parts)
+        .filter(|part_number| !pending.is_part_completed(*part_number))
+        .collect();
+    let upload_id = pending.upload_id.clone();
+    let pending = Mutex::new(pending);
+
+    // Parts finish out of order; the first failure drops the parts still in flight
+    try_join_all(remaining_parts.into_iter().map(|part_number| {
+        let (source, upload_id, pending, filename) = (&source, &upload_id, &pending, &filename);
+        async move {
+            let _part_slot =
+                acquire_while_running(control, reporter, UploadState::Uploading, || {
+                    pool.acquire_part()
+                })
+                .await?;
+
+            let label = format!("Part {}/{} of {}", part_number, num_parts, filename);
+            let mismatches = AtomicU32::new(0);
+            let (response, checksum) = control
+                .run(with_retry(&RetryPolicy::default(), &label, || async {
+                    // Re-read on every attempt: a streamed body can only be sent once
+                    let (bytes, checksum) = read_part(source, part_number).await?;
+                    let content_length = bytes.len() as u64;
+                    let progress = PartProgress::new(reporter);
+                    let body = progress.body(bytes);
+                    let response =
+                        upload_part(upload_id, part_number, body, content_length, &checksum)
+                            .await?;
+                    // A part whose checksum the server does not report cannot be
+                    // checked; the file is still verified as a whole on completion
+                    verify_checksum(&label, &checksum, response.checksum.as_deref())
+                        .map_err(|e| count_mismatch(e, &mismatches))?;
+                    progress.commit();
+                    Ok((response, checksum))
+                }))
+                .await?;
+
+            // Persist after every part so a restart loses at most the parts in flight
+            let mut pending = pending.lock().unwrap();
+            pending.complete_part(part_number, response.etag, checksum);
+            save_pending_upload(&pending)
+        }
+    }))
+    .await?;
+
+    let pending = pending.into_inner().unwrap();
+    let parts: Vec<CompletedPart> = (1..=num_parts)
+        .map(|part_number| {
+            CompletedPart::new(
+                part_number,
+                pending.completed_parts[&part_number].clone(),
+                pending.part_checksums[&part_number].clone(),
+            )
+        })
+        .collect();
+    let checksum = composite_checksum(parts.iter().map(|part| &part.checksum))?;
+
+    let response = control
+        .run(complete_upload(
+            &pending.upload_id,
+            &CompleteUploadRequest::new(parts, checksum.clone()),
+        ))
+        .await?;
+    let verified = match verify_checksum(&filename, &checksum, response.checksum.as_deref()) {
+        Ok(verified) => verified,
+        Err(e) => {
+            // Not retried: the server already holds parts that each matched.
+            // Keep the pending upload so the mismatch can be investigated
+            let e = AppError::UploadFailed(e.to_string());
+            eprintln!("{}", e);
+            return Err(e);
+        }
+    };
+
+    remove_pending_upload(&pending)?;
+    // The composite checksum doubles as the content hash for de-duplication
+    add_skipped_file(&filename, size as i64, device_id, &partial, &checksum)?;
+    let record = UploadRecord {
+        upload_id: pending.upload_id.clone(),
+        device_id: device_id.to_string(),
+        filename: filename.clone(),
+        size: size as i64,
+        checksum,
+        verified,
+        completed_at: unix_now(),
+    };
+    add_upload_record(record.clone())?;
+    println!("Uploaded {} (checksum verified: {})", filename, verified);
+
+    // Not cancellable: the upload is done and a deletion must not be cut short
+    let job = job.clone();
+    blocking(move || {
+        delete_after_upload(&job, &record);
+        Ok(())
+    })
+    .await?;
+
+    Ok(UploadState::Completed)
+}
+
+/// Whether the server reported the same checksum as ours. `Ok(false)` when
+/// it reported none, which leaves the upload unverified. Fails with
+/// `ChecksumMismatch` when it reported a different one.
+fn verify_checksum(label: &str, local: &str, server: Option<&str>) -> Result<bool, AppError> {
+    match server {
+        Some(server) if !server.eq_ignore_ascii_case(local) => Err(AppError::ChecksumMismatch(
+            format!("{}: sent {}, server has {}", label, local, server),
+        )),
+        Some(_) => Ok(true),
+        None => Ok(false),
+    }
+}
+
+/// Counts a checksum mismatch of a part. Past `MAX_CHECKSUM_RETRIES` it
+/// becomes an `UploadFailed`, which neither the part nor the queue retries.
+fn count_mismatch(error: AppError, mismatches: &AtomicU32) -> AppError {
+    if !matches!(error, AppError::ChecksumMismatch(_)) {
+        return error;
+    }
+    let count = mismatches.fetch_add(1, Ordering::Relaxed) + 1;
+    if count <= MAX_CHECKSUM_RETRIES {
+        return error;
+    }
+    AppError::UploadFailed(format!(
+        "{} (stored corrupted {} times in a row, giving up)",
+        error, count
+    ))
+}
+
+/// Holds the file while it or its batch is paused, showing it as paused in the
+/// UI until it continues in `state`.
+async fn wait_while_paused(
+    control: &FileControl,
+    reporter: &ProgressReporter,
+    state: UploadState,
+) -> Result<(), AppError> {
+    if control.is_paused() {
+        reporter.set_state(UploadState::Paused);
+        control.checkpoint().await?;
+        reporter.set_state(state);
+    }
+    control.checkpoint().await
+}
+
+/// Takes a slot of the pool once the file is running. A paused file holds no
+/// slot: one it got just as it was paused is handed back until it resumes.
+async fn acquire_while_running<'a, Fut>(
+    control: &FileControl,
+    reporter: &ProgressReporter,
+    state: UploadState,
+    acquire: impl Fn() -> Fut,
+) -> Result<SemaphorePermit<'a>, AppError>
+where
+    Fut: Future<Output = Result<SemaphorePermit<'a>, AppError>>,
+{
+    loop {
+        wait_while_paused(control, reporter, state).await?;
+        let slot = control.run(acquire()).await?;
+        if !control.is_paused() {
+            return Ok(slot);
+        }
+    }
+}
+
+/// Loads the saved progress for this file if it belongs to the same server
+/// upload, otherwise starts tracking a fresh one.
+fn resume_or_start(
+    upload_id: String,
+    device_id: &str,
+    filename: &str,
+    size: u64,
+    num_parts: i32,
+) -> Result<PendingUpload, AppError> {
+    match get_pending_upload(device_id, filename, size as i64)? {
+        Some(pending) if pending.upload_id == upload_id && pending.part_size == PART_SIZE => {
+            println!(
+                "Resuming {} at {}/{} parts",
+                filename,
+                pending.completed_parts.len(),
+                num_parts
+            );
+            Ok(pending)
+        }
+        _ => {
+            let pending = PendingUpload::new(
+                upload_id,
+                device_id.to_string(),
+                filename.to_string(),
+                size as i64,
+                PART_SIZE,
+                num_parts,
+            );
+            save_pending_upload(&pending)?;
+            Ok(pending)
+        }
+    }
+}
+
+/// Number of parts a file of `size` bytes is split into. Empty files still
+/// upload a single (empty) part.
+pub fn part_count(size: u64) -> i32 {
+    size.div_ceil(PART_SIZE).max(1) as i32
+}
+
+/// Length in bytes of part `part_number` (1-based) of a file of `size` bytes.
+fn part_len(part_number: i32, size: u64) -> u64 {
+    let offset = (part_number as u64 - 1) * PART_SIZE;
+    PART_SIZE.min(size.saturating_sub(offset))
+}
+
+/// Runs blocking file work (hashing, reading) off the async runtime.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::Internal(format!("Blocking task failed: {}", e)))?
+}
+
+/// Reads a part and computes its checksum off the async runtime.
+async fn read_part(
+    source: &Arc<UploadSource>,
+    part_number: i32,
+) -> Result<(Vec<u8>, String), AppError> {
+    let source = source.clone();
+    let offset = (part_number as u64 - 1) * PART_SIZE;
+    let len = part_len(part_number, source.size);
+
+    tokio::task::spawn_blocking(move || -> Result<(Vec<u8>, String), AppError> {
+        let buf = source.read(offset, len)?;
+        let checksum = sha256_hex(&buf);
+        Ok((buf, checksum))
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Failed to read part {}: {}", part_number, e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_is_one_part() {
+        assert_eq!(part_count(0), 1);
+        assert_eq!(part_len(1, 0), 0);
+    }
+
+    #[test]
+    fn parts_are_part_size_but_the_last() {
+        let size = 2 * PART_SIZE + 10;
+        assert_eq!(part_count(size), 3);
+        assert_eq!(part_len(1, size), PART_SIZE);
+        assert_eq!(part_len(2, size), PART_SIZE);
+        assert_eq!(part_len(3, size), 10);
+    }
+
+    #[test]
+    fn exact_multiple_has_no_empty_part() {
+        assert_eq!(part_count(PART_SIZE), 1);
+        assert_eq!(part_count(2 * PART_SIZE), 2);
+        assert_eq!(part_len(2, 2 * PART_SIZE), PART_SIZE);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_retryable() {
+        assert!(verify_checksum("part", "abc", Some("ABC")).unwrap());
+
+        let error = verify_checksum("part", "abc", Some("def")).unwrap_err();
+        assert!(matches!(error, AppError::ChecksumMismatch(_)));
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn missing_server_checksum_is_unverified() {
+        assert!(!verify_checksum("file", "abc", None).unwrap());
+    }
+
+    #[test]
+    fn checksum_mismatches_are_retried_a_fixed_number_of_times() {
+        let mismatches = AtomicU32::new(0);
+        let mismatch = || verify_checksum("part", "abc", Some("def")).unwrap_err();
+
+        for _ in 0..MAX_CHECKSUM_RETRIES {
+            let error = count_mismatch(mismatch(), &mismatches);
+            assert!(matches!(error, AppError::ChecksumMismatch(_)));
+        }
+
+        let error = count_mismatch(mismatch(), &mismatches);
+        assert!(matches!(error, AppError::UploadFailed(_)));
+        assert!(!error.is_retryable());
+    }
+}
@@ -3,7 +3,7 @@ interface UploadStatus {
   bytesUploaded: number;
   totalBytes: number;
   percentage: number;
-  status: 'pending' | 'uploading' | 'completed' | 'skipped' | 'failed';
+  status: 'pending' | 'uploading' | 'paused' | 'completed' | 'skipped' | 'failed' | 'cancelled';
   error?: string;
 }
 
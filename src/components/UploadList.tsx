@@ -12,7 +12,7 @@ export default function UploadList() {
   return (
     <div className="upload-list-container">
       {uploadArray.map(upload => (
-        <UploadItem key={upload.filename} upload={upload} />
+        <UploadItem key={upload.jobId} upload={upload} />
       ))}
     </div>
   );
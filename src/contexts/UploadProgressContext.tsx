@@ -27,16 +27,16 @@ export const UploadProgressProvider: React.FC<{ children: ReactNode }> = ({ chil
         const status = event.payload;
         setUploads((prev) => ({
           ...prev,
-          [status.filename]: status,
+          [status.jobId]: status,
         }));
       });
 
       // Listen for upload completion
-      unlistenComplete = await listen<{ filename: string }>('upload-complete', (event) => {
-        const { filename } = event.payload;
+      unlistenComplete = await listen<{ jobId: string; filename: string }>('upload-complete', (event) => {
+        const { jobId } = event.payload;
         setUploads((prev) => ({
           ...prev,
-          [filename]: { ...prev[filename], status: 'completed', percentage: 100 },
+          [jobId]: { ...prev[jobId], status: 'completed', percentage: 100 },
         }));
       });
 
@@ -46,11 +46,11 @@ export const UploadProgressProvider: React.FC<{ children: ReactNode }> = ({ chil
       });
 
       // Listen for upload errors
-      unlistenError = await listen<{ filename: string; error: string }>('upload-error', (event) => {
-        const { filename, error } = event.payload;
+      unlistenError = await listen<{ jobId: string; filename: string; error: string }>('upload-error', (event) => {
+        const { jobId, error } = event.payload;
         setUploads((prev) => ({
           ...prev,
-          [filename]: { ...prev[filename], status: 'failed', error },
+          [jobId]: { ...prev[jobId], status: 'failed', error },
         }));
       });
     };
@@ -1,5 +1,8 @@
 import {invoke} from "@tauri-apps/api/core";
+import {listen, UnlistenFn} from "@tauri-apps/api/event";
 import {UserInfo} from "../../rust-api/model/AuthResult.ts";
+import {DeviceCodePrompt} from "../../rust-api/model/DeviceCodePrompt.ts";
+import {OAuthConfig} from "../../rust-api/model/OAuthConfig.ts";
 export async function request(
     method: string,
     path: string,
@@ -14,10 +17,31 @@ export async function request(
   })
 }
 
+// Logs in first if needed, which waits until the user finishes in their browser
 export async function getUser(): Promise<UserInfo> {
   return invoke<UserInfo>("get_user");
 }
 
+// The saved login config, or the built-in defaults before one is saved
+export async function getLoginConfig(): Promise<OAuthConfig> {
+  return await invoke<OAuthConfig>("get_login_config");
+}
+
+// Logs out if the environment or client id changes
+export async function saveLoginConfig(config: OAuthConfig): Promise<void> {
+  await invoke("save_login_config", { config });
+}
+
+// Makes a pending getUser() fail with status Cancelled
+export async function cancelLogin(): Promise<void> {
+  await invoke("cancel_login");
+}
+
+// Fired when a login needs the user to enter a code in their browser
+export async function onDeviceCode(handler: (prompt: DeviceCodePrompt) => void): Promise<UnlistenFn> {
+  return await listen<DeviceCodePrompt>("login-device-code", (event) => handler(event.payload));
+}
+
 export async function logout(): Promise<void> {
   return invoke("clear_user_cache");
 }
\ No newline at end of file
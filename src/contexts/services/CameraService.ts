@@ -1,9 +1,56 @@
 import {invoke} from "@tauri-apps/api/core";
+import {listen, UnlistenFn} from "@tauri-apps/api/event";
+import {Camera} from "../../rust-api/model/Camera";
+import {CameraEvent} from "../../rust-api/model/CameraEvent";
+import {UploadJob} from "../../rust-api/model/UploadJob";
 
 
-export async function getCamera(): Promise<string> {
-  return await invoke<string>("get_camera");
+export async function getCameras(): Promise<Camera[]> {
+  return await invoke<Camera[]>("get_cameras");
+}
+// Omit deviceId when only one camera is connected
+export async function getCamera(deviceId?: string): Promise<string> {
+  return await invoke<string>("get_camera", { deviceId });
+}
+// Queues every file on the camera; the backend uploads them in the background
+export async function uploadAllFiles(deviceId?: string): Promise<UploadJob[]> {
+  return await invoke<UploadJob[]>("get_camera_files", { deviceId });
+}
+// Omit jobId to act on the whole batch
+export async function pauseUpload(jobId?: string): Promise<void> {
+  await invoke("pause_upload", { jobId });
+}
+
+export async function resumeUpload(jobId?: string): Promise<void> {
+  await invoke("resume_upload", { jobId });
+}
+
+export async function cancelUpload(jobId?: string): Promise<void> {
+  await invoke("cancel_upload", { jobId });
+}
+
+export async function getUploadJobs(): Promise<UploadJob[]> {
+  return await invoke<UploadJob[]>("get_upload_jobs");
+}
+
+// Higher priorities are uploaded first
+export async function reorderUploadJob(id: string, priority: number): Promise<UploadJob> {
+  return await invoke<UploadJob>("reorder_upload_job", { id, priority });
+}
+
+export async function retryUploadJob(id: string): Promise<UploadJob> {
+  return await invoke<UploadJob>("retry_upload_job", { id });
+}
+
+export async function removeUploadJob(id: string): Promise<void> {
+  await invoke("remove_upload_job", { id });
+}
+
+// Fired when a supported camera is plugged in, including ones already connected at startup
+export async function onCameraConnected(handler: (event: CameraEvent) => void): Promise<UnlistenFn> {
+  return await listen<CameraEvent>("camera-connected", (event) => handler(event.payload));
+}
+
+export async function onCameraDisconnected(handler: (event: CameraEvent) => void): Promise<UnlistenFn> {
+  return await listen<CameraEvent>("camera-disconnected", (event) => handler(event.payload));
 }
-export async function uploadAllFiles(): Promise<void> {
-  await invoke("get_camera_files");
-}
\ No newline at end of file
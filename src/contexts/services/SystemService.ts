@@ -1,5 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import {UserInfo} from "../../rust-api/model/AuthResult.ts";
+import {Settings} from "../../rust-api/model/Settings.ts";
+import {DeletionRecord} from "../../rust-api/model/DeletionRecord.ts";
 
 export async function checkAuth(): Promise<UserInfo> {
   return await invoke<UserInfo>("check_auth");
@@ -11,4 +13,17 @@ export async function getInitials(fullName?: string): Promise<string> {
 
 export async function deleteData(): Promise<void> {
   await invoke("clear_cache");
-}
\ No newline at end of file
+}
+
+export async function getSettings(): Promise<Settings> {
+  return await invoke<Settings>("get_settings");
+}
+
+export async function saveSettings(settings: Settings): Promise<void> {
+  await invoke("save_settings", { settings });
+}
+
+// Files deleted (or, in dry-run mode, that would have been) after upload
+export async function getDeletionLog(): Promise<DeletionRecord[]> {
+  return await invoke<DeletionRecord[]>("get_deletion_log");
+}
@@ -0,0 +1,46 @@
+import {FileInfo} from "./FileInfo";
+
+export interface StorageLayout {
+  folders: string[];
+  extensions: string[];
+  dualFileMarkers: string[];
+}
+
+export interface CameraInfo {
+  vendor: 'Insta' | 'Theta' | 'GoPro';
+  vendorId: number;
+  productId: number | null;
+  productName: string | null;
+  device: string;
+  layout: StorageLayout;
+  preferredBackend: 'ptp' | 'massStorage' | null;
+}
+
+export interface UsbDevice {
+  bus: number;
+  address: number;
+  ports: number[];
+  vendorId: number;
+  productId: number;
+  serial: string | null;
+  product: string | null;
+}
+
+export interface DeviceIdentity {
+  serial: string | null;
+  firmware: string | null;
+  model: string | null;
+  volumeId: string | null;
+}
+
+export interface Camera {
+  device_id: string;
+  usb_id: string;
+  info: CameraInfo;
+  usb: UsbDevice;
+  identity: DeviceIdentity;
+  backend: string | null;
+  mount_point: string | null;
+  files: FileInfo[];
+  access_error: string | null;
+}
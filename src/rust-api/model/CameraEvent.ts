@@ -0,0 +1,8 @@
+import {CameraInfo, UsbDevice} from "./Camera";
+
+// Payload of `camera-connected` and `camera-disconnected`
+export interface CameraEvent {
+  usbId: string;
+  info: CameraInfo;
+  usb: UsbDevice;
+}
@@ -0,0 +1,9 @@
+export interface DeletionRecord {
+  uploadId: string;
+  deviceId: string;
+  filename: string;
+  size: number;
+  checksum: string;
+  dryRun: boolean;
+  deletedAt: number;
+}
@@ -0,0 +1,9 @@
+// Payload of `login-device-code`
+export interface DeviceCodePrompt {
+  userCode: string;
+  verificationUri: string;
+  // Verification URL with the user code filled in, for a link or QR code
+  verificationUriComplete: string;
+  // Seconds since the Unix epoch
+  expiresAt: number;
+}
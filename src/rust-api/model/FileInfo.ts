@@ -3,4 +3,8 @@ export interface FileInfo {
   filename: string;
   size: number;
   contentType: string;
-}
\ No newline at end of file
+  folder: string;
+  mtime: number | null;
+  // Shared by the files of a capture saved as one file per lens
+  capture: string | null;
+}
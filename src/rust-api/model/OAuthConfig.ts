@@ -0,0 +1,14 @@
+export type AuthEnv = 'Dev' | 'Prod';
+export type AuthScope = 'Openid' | 'Email' | 'OfflineAccess';
+export type AuthFlow = 'DeviceCode' | 'AuthorizationCode';
+
+export interface OAuthConfig {
+  // Null uses the client id built in for env; Prod has none built in
+  client_id: string | null;
+  env: AuthEnv;
+  // OfflineAccess is always requested
+  scopes: AuthScope[];
+  flow: AuthFlow;
+  // Loopback port of the AuthorizationCode flow; null picks any free port
+  redirect_port: number | null;
+}
@@ -0,0 +1,7 @@
+export interface Settings {
+  maxConcurrentFiles: number;
+  maxConcurrentParts: number;
+  deleteAfterUpload: boolean;
+  deletionDryRun: boolean;
+  stageFiles: boolean;
+}
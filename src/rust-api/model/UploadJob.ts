@@ -0,0 +1,22 @@
+export type JobState =
+  | 'queued'
+  | 'running'
+  | 'completed'
+  | 'skipped'
+  | 'failed'
+  | 'cancelled';
+
+export interface UploadJob {
+  id: string;
+  deviceId: string;
+  filename: string;
+  size: number;
+  state: JobState;
+  priority: number;
+  attempts: number;
+  lastError: string | null;
+  // Seconds since the Unix epoch before which a retry does not start
+  notBefore: number | null;
+  createdAt: number;
+  updatedAt: number;
+}
@@ -1,9 +1,10 @@
 export interface UploadStatus {
+  jobId: string;
   filename: string;
   bytesUploaded: number;
   totalBytes: number;
   percentage: number;
-  status: 'pending' | 'uploading' | 'completed' | 'skipped' | 'failed';
+  status: 'pending' | 'uploading' | 'paused' | 'completed' | 'skipped' | 'failed' | 'cancelled';
   error?: string;
 }
 